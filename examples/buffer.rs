@@ -1,9 +1,9 @@
-/// Example implementation of a rendezvous buffer.
-///
-/// The values are passed directly from a sender to a receiver. This example
-/// most importantly demonstrates error handling related to channels not being
-/// able to send messages after their controller has shut down. Failing to
-/// correctly handle cases like that will cause threads to panic.
+//! Example implementation of a rendezvous buffer.
+//!
+//! The values are passed directly from a sender to a receiver. This example
+//! most importantly demonstrates error handling related to channels not being
+//! able to send messages after their controller has shut down. Failing to
+//! correctly handle cases like that will cause threads to panic.
 
 use std::any::Any;
 use std::thread;
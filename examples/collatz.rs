@@ -1,18 +1,18 @@
-/// Junction computing the Collatz sequence for a given number up to a
-/// maximum number of iterations.
-///
-/// This example demonstrates how finite iterations can be implemented using
-/// junctions and join patterns. It also demonstrates how user-defined types
-/// can be used with the channels that this library provides and how to return
-/// a final result of an entirely asynchronous calculation. Run the example
-/// by executing the following command in the repository root:
-///
-/// $ cargo run --example collatz -- <initiat value> <maximum iterations>
-///
-/// replacing the arguments after the "--" with the desired integer values.
-/// Refer to the following Wikipedia article for the mathematical background:
-///
-/// https://en.wikipedia.org/wiki/Collatz_conjecture
+//! Junction computing the Collatz sequence for a given number up to a
+//! maximum number of iterations.
+//!
+//! This example demonstrates how finite iterations can be implemented using
+//! junctions and join patterns. It also demonstrates how user-defined types
+//! can be used with the channels that this library provides and how to return
+//! a final result of an entirely asynchronous calculation. Run the example
+//! by executing the following command in the repository root:
+//!
+//! $ cargo run --example collatz -- <initiat value> <maximum iterations>
+//!
+//! replacing the arguments after the "--" with the desired integer values.
+//! Refer to the following Wikipedia article for the mathematical background:
+//!
+//! https://en.wikipedia.org/wiki/Collatz_conjecture
 
 use std::env;
 
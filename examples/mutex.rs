@@ -1,7 +1,7 @@
-/// Example implementation of a mutually exclusive lock, or mutex.
-///
-/// This code provides evidence that concurrent code using locks can equally
-/// be implemented using junctions and join patterns.
+//! Example implementation of a mutually exclusive lock, or mutex.
+//!
+//! This code provides evidence that concurrent code using locks can equally
+//! be implemented using junctions and join patterns.
 
 use std::thread;
 
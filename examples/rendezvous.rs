@@ -1,11 +1,11 @@
-/// Rendezvous implementation for n entities to meet up.
-///
-/// Implmentation is a translation of the rendezvous written in Polyphonic C#
-/// in the "Jingle bells: Solving the santa claus problem in polyphonic c#"
-/// paper by Benton.
-///
-/// Link to the paper:
-/// https://www.researchgate.net/profile/Nick_Benton2/publication/2569067_Jingle_Bells_Solving_the_Santa_Claus_Problem_in/links/0c9605264f92520a08000000/Jingle-Bells-Solving-the-Santa-Claus-Problem-in.pdf
+//! Rendezvous implementation for n entities to meet up.
+//!
+//! Implmentation is a translation of the rendezvous written in Polyphonic C#
+//! in the "Jingle bells: Solving the santa claus problem in polyphonic c#"
+//! paper by Benton.
+//!
+//! Link to the paper:
+//! https://www.researchgate.net/profile/Nick_Benton2/publication/2569067_Jingle_Bells_Solving_the_Santa_Claus_Problem_in/links/0c9605264f92520a08000000/Jingle-Bells-Solving-the-Santa-Claus-Problem-in.pdf
 
 use std::thread;
 
@@ -39,7 +39,7 @@ pub fn rendezvous() -> (ControllerHandle, BidirChannel<u32, ()>, RecvChannel<()>
         if n == 1 {
             all_gone_clone.send(()).unwrap();
         } else {
-            token_clone.send(n - 1);
+            token_clone.send(n - 1).unwrap();
         }
     });
 
@@ -69,7 +69,7 @@ fn main() {
     let (mut ch, accept_n, entry) = rendezvous();
     println!("Done constructing rendezvous!");
 
-    for i in 0..num_entries {
+    for _ in 0..num_entries {
         let entry_clone = entry.clone();
         thread::spawn(move || {
             println!("Sending entry...");
@@ -1,9 +1,9 @@
-/// Santa Claus Problem in Rusty Junctions.
-///
-/// The code below is based on the paper "Jingle Bells:  Solving the Santa Claus
-/// Problem in Polyphonic C#" by Nick Benton
-///
-/// The paper: https://www.researchgate.net/profile/Nick_Benton2/publication/2569067_Jingle_Bells_Solving_the_Santa_Claus_Problem_in/links/0c9605264f92520a08000000/Jingle-Bells-Solving-the-Santa-Claus-Problem-in.pdf
+//! Santa Claus Problem in Rusty Junctions.
+//!
+//! The code below is based on the paper "Jingle Bells:  Solving the Santa Claus
+//! Problem in Polyphonic C#" by Nick Benton
+//!
+//! The paper: https://www.researchgate.net/profile/Nick_Benton2/publication/2569067_Jingle_Bells_Solving_the_Santa_Claus_Problem_in/links/0c9605264f92520a08000000/Jingle-Bells-Solving-the-Santa-Claus-Problem-in.pdf
 
 use rand::Rng;
 
@@ -53,28 +53,20 @@ fn main() {
     // Asynchronous channel to signal that enough reindeer are ready.
     let reindeer_ready = santa.send_channel::<()>();
 
-    // Asynchronous channel to signal that not enough reindeer are ready.
-    // Used for prioritisation.
-    let reindeer_not_ready = santa.send_channel::<()>();
-
-    // Synchronous channel to match and consume a reindeer_not_ready message.
-    // Used for prioritisation.
-    let clear_reindeer_not_ready = santa.recv_channel::<()>();
-
     // Asynchronous channel to signal that enough elves are ready.
     let elves_ready = santa.send_channel::<()>();
 
     // Rendezvous channels to let elves into room.
-    let (mut ch_1, room_in_accept_n, room_in_entry) = rendezvous();
+    let (_ch_1, room_in_accept_n, room_in_entry) = rendezvous();
 
     // Rendezvous channels to let elves out of room.
-    let (mut ch_2, room_out_accept_n, room_out_entry) = rendezvous();
+    let (_ch_2, room_out_accept_n, room_out_entry) = rendezvous();
 
     // Rendezvous channels to harness the reindeer.
-    let (mut ch_3, harness_accept_n, harness_entry) = rendezvous();
+    let (_ch_3, harness_accept_n, harness_entry) = rendezvous();
 
     // Rendezvous channels to unharness the reindeer.
-    let (mut ch_4, unharness_accept_n, unharness_entry) = rendezvous();
+    let (_ch_4, unharness_accept_n, unharness_entry) = rendezvous();
 
     /***********************
      * Elves Join Patterns *
@@ -104,14 +96,12 @@ fn main() {
     // Count up how many reindeer are waiting and possibly send ready message.
     let reindeer_ready_clone = reindeer_ready.clone();
     let reindeer_waiting_clone = reindeer_waiting.clone();
-    let clear_reindeer_not_ready_clone = clear_reindeer_not_ready.clone();
     reindeer
         .when(&reindeer_waiting)
         .and_recv(&reindeer_back)
         .then_do(move |r| {
             if r == 8 {
                 // Last reindeer just came back.
-                clear_reindeer_not_ready_clone.recv().unwrap();
                 reindeer_ready_clone.send(()).unwrap();
                 println!("<Reindeer> All 9 assembled!");
             } else {
@@ -125,18 +115,13 @@ fn main() {
      ***********************/
 
     // Enough elves are ready so let's consult with them.
-    let reindeer_not_ready_clone = reindeer_not_ready.clone();
     let elves_waiting_clone = elves_waiting.clone();
     santa
         .when(&elves_ready)
-        .and(&reindeer_not_ready)
         .and_recv(&wait_to_be_woken)
-        .then_do(move |_, _| {
+        .then_do(move |_| {
             let mut rng = rand::thread_rng();
 
-            // Reindeer will still not be ready so resend just consumed message.
-            reindeer_not_ready_clone.send(()).unwrap();
-
             // Show 3 elves into the office once all are ready.
             println!("<Santa> Woken by elves, now showing them in!");
             room_in_accept_n.send_recv(3).unwrap();
@@ -156,12 +141,13 @@ fn main() {
             println!("<Santa> Elf group shown out!");
         });
 
-    // Enough reindeer are ready so let's deliver some presents.
-    let reindeer_not_ready_clone = reindeer_not_ready.clone();
+    // Enough reindeer are ready so let's deliver some presents. Reindeer
+    // take priority over elves should both be ready at the same time.
     let reindeer_waiting_clone = reindeer_waiting.clone();
     santa
         .when(&reindeer_ready)
         .and_recv(&wait_to_be_woken)
+        .priority(1)
         .then_do(move |_| {
             let mut rng = rand::thread_rng();
 
@@ -170,10 +156,6 @@ fn main() {
             harness_accept_n.send_recv(9).unwrap();
             println!("<Santa> Reindeer harnessed!");
 
-            // Reindeer are harnessed, so they are no longer ready.
-            // Used for prioritisation.
-            reindeer_not_ready_clone.send(()).unwrap();
-
             // Reset how many reindeer are waiting.
             reindeer_waiting_clone.send(0).unwrap();
 
@@ -188,18 +170,12 @@ fn main() {
             println!("<Santa> Reindeer unharnessed!");
         });
 
-    // Clear the reindeer_not_ready message. Used for prioritisation.
-    santa
-        .when(&reindeer_not_ready)
-        .and_recv(&clear_reindeer_not_ready)
-        .then_do(|_| {});
-
     /*******************************
      * Start North Pole Operations *
      *******************************/
 
     // Spawn in the 10 elves and send the initial number of waiting ones.
-    for i in 0..10 {
+    for _ in 0..10 {
         new_elf(
             elf_queue.clone(),
             room_in_entry.clone(),
@@ -208,9 +184,8 @@ fn main() {
     }
     elves_waiting.send(0).unwrap();
 
-    // Spawn in the 9 reindeer, send the initial number of waiting ones and
-    // send that they are not ready yet.
-    for i in 0..9 {
+    // Spawn in the 9 reindeer and send the initial number of waiting ones.
+    for _ in 0..9 {
         new_reindeer(
             reindeer_back.clone(),
             harness_entry.clone(),
@@ -218,21 +193,14 @@ fn main() {
         );
     }
     reindeer_waiting.send(0).unwrap();
-    reindeer_not_ready.send(()).unwrap();
 
     // Santa keeps napping until something comes up.
     println!("<North Pole> Starting operations!");
-    while true {
+    loop {
         println!("<Santa> Starting a nap, waiting to be woken...");
         wait_to_be_woken.recv().unwrap();
         println!("<Santa> Woken from nap!");
     }
-
-    // Clean up the controller resources in the background manually at the end.
-    ch_1.stop();
-    ch_2.stop();
-    ch_3.stop();
-    ch_4.stop();
 }
 
 // Create a new elf in a new thread.
@@ -246,7 +214,7 @@ fn new_elf(
         // Random number generator for working and consulting times.
         let mut rng = rand::thread_rng();
 
-        while true {
+        loop {
             // Work for 0 to 10 seconds, i.e. pause thread.
             println!("<Elf> Going to work now!");
             thread::sleep(Duration::from_secs(rng.gen_range(0, 10)));
@@ -286,7 +254,7 @@ fn new_reindeer(
         // Random number generator for holiday and delivery times.
         let mut rng = rand::thread_rng();
 
-        while true {
+        loop {
             // Go on holiday for 0 to 10 seconds, i.e. pause thread.
             println!("<Reindeer> Going on holiday now!");
             thread::sleep(Duration::from_secs(rng.gen_range(0, 10)));
@@ -341,7 +309,7 @@ pub fn rendezvous() -> (ControllerHandle, BidirChannel<u32, ()>, RecvChannel<()>
         if n == 1 {
             all_gone_clone.send(()).unwrap();
         } else {
-            token_clone.send(n - 1);
+            token_clone.send(n - 1).unwrap();
         }
     });
 
@@ -1,5 +1,5 @@
-/// Simple storage cell implementation to demonstrate every available
-/// channel as well as join patterns with repeated channels.
+//! Simple storage cell implementation to demonstrate every available
+//! channel as well as join patterns with repeated channels.
 
 use rusty_junctions::Junction;
 
@@ -54,7 +54,7 @@ fn main() {
     cell.when(&val).and_recv(&get).then_do(move |v| {
         println!(">> val-get pattern fired with v={}!", v);
 
-        get_val.send(v.clone()).unwrap();
+        get_val.send(v).unwrap();
 
         v
     });
@@ -1,4 +1,4 @@
-/// Simple toy example to demonstrate the basic API of the library.
+//! Simple toy example to demonstrate the basic API of the library.
 
 // The only struct that needs to be brought into score is the Junction itself.
 use rusty_junctions::Junction;
@@ -48,13 +48,47 @@ where
     ///
     /// Retrieve `Some` of the least recently added value for the given key
     /// if there is at least one available, otherwise return `None`.
+    #[cfg(test)]
     pub(crate) fn retrieve(&mut self, key: &K) -> Option<V> {
         self.items.get_mut(key)?.pop_front()
     }
 
+    /// Return a reference to the value at the given position for the given key.
+    ///
+    /// Values are ordered from least to most recently added, so position `0`
+    /// is the value that would be retrieved next. Return `None` if there is
+    /// no value at that position.
+    pub(crate) fn peek_at(&self, key: &K, index: usize) -> Option<&V> {
+        self.items.get(key)?.get(index)
+    }
+
+    /// Retrieve the value at the given position for the given key, if possible.
+    ///
+    /// Values are ordered from least to most recently added, so position `0`
+    /// is the value that would be retrieved by `retrieve`. The order of the
+    /// remaining values is preserved.
+    pub(crate) fn retrieve_at(&mut self, key: &K, index: usize) -> Option<V> {
+        self.items.get_mut(key)?.remove(index)
+    }
+
+    /// Retrieve the first value for the given key satisfying a predicate.
+    ///
+    /// Return `None` if no value for the given key satisfies the predicate.
+    /// The order of the remaining values is preserved.
+    pub(crate) fn retrieve_where<P>(&mut self, key: &K, predicate: P) -> Option<V>
+    where
+        P: Fn(&V) -> bool,
+    {
+        let queue = self.items.get_mut(key)?;
+        let index = queue.iter().position(predicate)?;
+
+        queue.remove(index)
+    }
+
     /// Return true if there are values for the given key.
+    #[cfg(test)]
     pub(crate) fn contains_items(&self, key: &K) -> bool {
-        self.items.get(key).map_or(false, |q| !q.is_empty())
+        self.items.get(key).is_some_and(|q| !q.is_empty())
     }
 
     /// Return the number of values stored for the given key.
@@ -148,6 +182,39 @@ mod tests {
         assert!(second.is_none());
     }
 
+    #[test]
+    fn test_retrieving_where_predicate_holds() {
+        // Given:
+        let mut bag: Bag<usize, char> = Bag::new();
+
+        // When:
+        bag.add(217, 'O');
+        bag.add(217, 'v');
+        bag.add(217, 'e');
+
+        let actual = bag.retrieve_where(&217, |&c| c == 'v');
+
+        // Then:
+        assert_eq!('v', actual.unwrap());
+        assert_eq!('O', bag.retrieve(&217).unwrap());
+        assert_eq!('e', bag.retrieve(&217).unwrap());
+    }
+
+    #[test]
+    fn test_retrieving_where_predicate_never_holds() {
+        // Given:
+        let mut bag: Bag<usize, char> = Bag::new();
+
+        // When:
+        bag.add(217, 'O');
+
+        let actual = bag.retrieve_where(&217, |&c| c == 'v');
+
+        // Then:
+        assert!(actual.is_none());
+        assert_eq!(1, bag.count_items(&217));
+    }
+
     #[test]
     fn test_contains_items_with_no_item() {
         // Given:
@@ -267,4 +334,74 @@ mod tests {
         // Then:
         assert_eq!(0, bag.count_items(&217));
     }
+
+    #[test]
+    fn test_peek_at_non_existent() {
+        // Given:
+        let bag: Bag<usize, char> = Bag::new();
+
+        // Then:
+        assert!(bag.peek_at(&217, 0).is_none());
+    }
+
+    #[test]
+    fn test_peek_at_existing() {
+        // Given:
+        let mut bag: Bag<usize, char> = Bag::new();
+
+        // When:
+        bag.add(217, 'O');
+        bag.add(217, 'v');
+
+        // Then:
+        assert_eq!('O', *bag.peek_at(&217, 0).unwrap());
+        assert_eq!('v', *bag.peek_at(&217, 1).unwrap());
+        assert!(bag.peek_at(&217, 2).is_none());
+    }
+
+    #[test]
+    fn test_peek_at_does_not_retrieve() {
+        // Given:
+        let mut bag: Bag<usize, char> = Bag::new();
+
+        // When:
+        bag.add(217, 'O');
+        let _v = bag.peek_at(&217, 0);
+
+        // Then:
+        assert_eq!(1, bag.count_items(&217));
+    }
+
+    #[test]
+    fn test_retrieve_at_middle() {
+        // Given:
+        let mut bag: Bag<usize, char> = Bag::new();
+
+        // When:
+        bag.add(217, 'O');
+        bag.add(217, 'v');
+        bag.add(217, 'e');
+
+        let actual = bag.retrieve_at(&217, 1);
+
+        // Then:
+        assert_eq!('v', actual.unwrap());
+        assert_eq!('O', bag.retrieve(&217).unwrap());
+        assert_eq!('e', bag.retrieve(&217).unwrap());
+    }
+
+    #[test]
+    fn test_retrieve_at_out_of_range() {
+        // Given:
+        let mut bag: Bag<usize, char> = Bag::new();
+
+        // When:
+        bag.add(217, 'O');
+
+        let actual = bag.retrieve_at(&217, 1);
+
+        // Then:
+        assert!(actual.is_none());
+        assert_eq!(1, bag.count_items(&217));
+    }
 }
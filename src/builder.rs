@@ -0,0 +1,345 @@
+//! Builder to configure a `Junction` before starting it.
+//!
+//! `Junction::new` and `Junction::new_tokio` start a `Junction` with the
+//! default options. Any other configuration, from the threads running the
+//! functions of fired Join Patterns to the capacity of its channels, goes
+//! through a `JunctionBuilder`.
+
+use std::any::Any;
+use std::sync::Arc;
+
+use super::controller::Controller;
+use super::executors::{Executor, ThreadPool};
+use super::junction::Junction;
+use super::policies::{FiringPolicy, LeastRecentlyFired};
+use super::pool::DEFAULT_POOL_SIZE;
+use super::types::functions::PanicHandler;
+
+/// Builder for a `Junction` with custom options.
+///
+/// Created by `Junction::builder`. Options not set keep the defaults
+/// described for `Junction::new`.
+pub struct JunctionBuilder {
+    thread_name: Option<String>,
+    stack_size: Option<usize>,
+    pool_size: usize,
+    executor: Option<Box<dyn Executor>>,
+    firing_policy: Box<dyn FiringPolicy>,
+    panic_handler: Option<PanicHandler>,
+    send_channel_capacity: Option<usize>,
+}
+
+impl JunctionBuilder {
+    pub(crate) fn new() -> JunctionBuilder {
+        JunctionBuilder {
+            thread_name: None,
+            stack_size: None,
+            pool_size: DEFAULT_POOL_SIZE,
+            executor: None,
+            firing_policy: Box::new(LeastRecentlyFired),
+            panic_handler: None,
+            send_channel_capacity: None,
+        }
+    }
+
+    /// Name the threads of the `Junction` with the given prefix.
+    ///
+    /// The control thread is named by the prefix followed by `-controller`
+    /// and the worker threads of its pool by the prefix followed by
+    /// `-worker-` and their number, e.g. `prefix-worker-0`. This makes the
+    /// threads of different `Junction`s easy to tell apart in debuggers and
+    /// profilers.
+    pub fn thread_name<S>(mut self, prefix: S) -> JunctionBuilder
+    where
+        S: Into<String>,
+    {
+        self.thread_name = Some(prefix.into());
+
+        self
+    }
+
+    /// Set the stack size in bytes of the threads running the functions of
+    /// fired Join Patterns.
+    ///
+    /// Has no effect if the `Junction` is given an `Executor`.
+    pub fn stack_size(mut self, stack_size: usize) -> JunctionBuilder {
+        self.stack_size = Some(stack_size);
+
+        self
+    }
+
+    /// Set the maximum number of threads running the functions of fired
+    /// Join Patterns.
+    ///
+    /// Once all of them are busy, further firings wait for one of them to
+    /// become available. Note that this can deadlock if all workers are
+    /// blocked waiting on Join Patterns that have yet to run. Has no effect
+    /// if the `Junction` is given an `Executor`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pool_size` is zero.
+    pub fn pool_size(mut self, pool_size: usize) -> JunctionBuilder {
+        if pool_size == 0 {
+            panic!("ThreadPool needs at least one worker thread!");
+        }
+
+        self.pool_size = pool_size;
+
+        self
+    }
+
+    /// Run the functions of fired Join Patterns with the given `Executor`.
+    ///
+    /// Replaces the pool of worker threads owned by the `Junction`. Join
+    /// Patterns with an `Executor` of their own are not affected.
+    pub fn executor<E>(mut self, executor: E) -> JunctionBuilder
+    where
+        E: Executor + 'static,
+    {
+        self.executor = Some(Box::new(executor));
+
+        self
+    }
+
+    /// Decide with the given `FiringPolicy` which Join Pattern to fire
+    /// whenever multiple Join Patterns of the same priority could fire at
+    /// the same time.
+    pub fn firing_policy<P>(mut self, firing_policy: P) -> JunctionBuilder
+    where
+        P: FiringPolicy + 'static,
+    {
+        self.firing_policy = Box::new(firing_policy);
+
+        self
+    }
+
+    /// Hand panics in the functions of fired Join Patterns to the given
+    /// handler if no caller is waiting on a reply, e.g. for Join Patterns
+    /// consisting of `SendChannel`s only.
+    ///
+    /// The handler is given the payload of the panic and runs on the thread
+    /// that ran the function, or polled the `Future` of an asynchronous one.
+    /// Callers waiting on a reply receive `JunctionError::BodyPanicked` with
+    /// the panic message instead. Without a handler, such panics
+    /// are only reported by the panic hook of the standard library.
+    pub fn panic_handler<F>(mut self, panic_handler: F) -> JunctionBuilder
+    where
+        F: Fn(Box<dyn Any + Send>) + Send + Sync + 'static,
+    {
+        self.panic_handler = Some(Arc::new(panic_handler));
+
+        self
+    }
+
+    /// Bound every `SendChannel` created by `Junction::send_channel` to at
+    /// most `capacity` messages waiting to be consumed by a Join Pattern.
+    ///
+    /// Sending on a full channel blocks until a fired Join Pattern makes
+    /// room, see `SendChannel::send` and `SendChannel::try_send`. Channels
+    /// created by `Junction::bounded_send_channel` keep their own capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn send_channel_capacity(mut self, capacity: usize) -> JunctionBuilder {
+        if capacity == 0 {
+            panic!("Bounded SendChannel needs a capacity of at least one message!");
+        }
+
+        self.send_channel_capacity = Some(capacity);
+
+        self
+    }
+
+    /// Create the `Junction` and start its control thread in the background.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the control thread could not be spawned.
+    pub fn build(self) -> Junction {
+        let controller_thread_name = self
+            .thread_name
+            .as_ref()
+            .map(|prefix| format!("{}-controller", prefix));
+
+        let send_channel_capacity = self.send_channel_capacity;
+
+        Junction::start(
+            self.into_controller(),
+            controller_thread_name,
+            send_channel_capacity,
+        )
+    }
+
+    /// Create the `Junction` with its `Controller` running as an async task
+    /// on the current tokio runtime.
+    ///
+    /// See `Junction::new_tokio` for the requirements on the runtime. As no
+    /// control thread is spawned, only the worker threads are named.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    #[cfg(feature = "tokio")]
+    pub fn build_tokio(self) -> Junction {
+        let send_channel_capacity = self.send_channel_capacity;
+
+        Junction::start_task(self.into_controller(), send_channel_capacity)
+    }
+
+    /// Create the `Controller` of the `Junction` with the options set.
+    fn into_controller(self) -> Controller {
+        let executor = match self.executor {
+            Some(executor) => executor,
+            None => {
+                let mut pool = ThreadPool::new(self.pool_size);
+
+                if let Some(prefix) = self.thread_name {
+                    pool = pool.thread_name(prefix);
+                }
+
+                if let Some(stack_size) = self.stack_size {
+                    pool = pool.stack_size(stack_size);
+                }
+
+                Box::new(pool)
+            }
+        };
+
+        Controller::new(self.firing_policy, executor, self.panic_handler)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::JunctionError;
+    use crate::executors::Inline;
+    use crate::policies::DeclarationOrder;
+
+    use std::sync::mpsc::channel;
+    use std::sync::Mutex;
+    use std::thread;
+
+    #[test]
+    fn test_threads_are_named_with_prefix() {
+        // Given:
+        let mut j = Junction::builder()
+            .thread_name("cell")
+            .stack_size(256 * 1024)
+            .build();
+        let mut controller_handle = j.controller_handle().unwrap();
+        let a = j.send_channel::<()>();
+        let (tx, rx) = channel::<Option<String>>();
+
+        j.when(&a)
+            .then_do(move |_| tx.send(thread::current().name().map(String::from)).unwrap());
+
+        // When:
+        a.send(()).unwrap();
+
+        // Then:
+        assert_eq!(
+            Some("cell-controller"),
+            controller_handle.thread().unwrap().name()
+        );
+        assert_eq!(Some(String::from("cell-worker-0")), rx.recv().unwrap());
+
+        controller_handle.stop();
+    }
+
+    #[test]
+    fn test_executor_and_firing_policy() {
+        // Given:
+        let mut j = Junction::builder()
+            .executor(Inline)
+            .firing_policy(DeclarationOrder)
+            .build();
+        let mut controller_handle = j.controller_handle().unwrap();
+        let a = j.send_channel::<()>();
+        let (tx, rx) = channel::<(&str, thread::ThreadId)>();
+        let tx_clone = tx.clone();
+
+        j.when(&a)
+            .then_do(move |_| tx.send(("first", thread::current().id())).unwrap());
+        j.when(&a)
+            .then_do(move |_| tx_clone.send(("second", thread::current().id())).unwrap());
+
+        // When:
+        a.send(()).unwrap();
+        a.send(()).unwrap();
+
+        // Then:
+        let control_thread_id = controller_handle.thread().unwrap().id();
+
+        assert_eq!(("first", control_thread_id), rx.recv().unwrap());
+        assert_eq!(("first", control_thread_id), rx.recv().unwrap());
+
+        controller_handle.stop();
+    }
+
+    #[test]
+    fn test_panic_handler_receives_send_only_panics() {
+        // Given:
+        let (tx, rx) = channel::<String>();
+        let tx = Mutex::new(tx);
+        let j = Junction::builder()
+            .panic_handler(move |payload| {
+                let message = payload.downcast::<&str>().unwrap().to_string();
+                tx.lock().unwrap().send(message).unwrap();
+            })
+            .build();
+        let a = j.send_channel::<()>();
+        let get = j.recv_channel::<()>();
+
+        j.when(&a).then_do(|_| panic!("send-only"));
+        j.when_recv(&get).then_do(|| panic!("synchronous"));
+
+        // When:
+        a.send(()).unwrap();
+        let reply = get.recv();
+
+        // Then:
+        assert_eq!("send-only", rx.recv().unwrap());
+        assert_eq!(
+            Err(JunctionError::BodyPanicked(String::from("synchronous"))),
+            reply
+        );
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty_pool() {
+        Junction::builder().pool_size(0);
+    }
+
+    #[test]
+    fn test_send_channels_are_bounded() {
+        // Given:
+        let j = Junction::builder().send_channel_capacity(1).build();
+        let a = j.send_channel::<u32>();
+        let b = j.send_channel::<()>();
+        let c = j.bounded_send_channel::<u32>(2);
+
+        j.when(&a).and(&b).then_do(|_, _| {});
+        j.when(&c).and(&b).then_do(|_, _| {});
+
+        // When:
+        let sent_a = (a.try_send(1), a.try_send(2));
+        let sent_c = (c.try_send(1), c.try_send(2), c.try_send(3));
+
+        // Then:
+        assert!(sent_a.0.is_ok());
+        assert_eq!(JunctionError::Full, sent_a.1.unwrap_err().error());
+        assert!(sent_c.0.is_ok() && sent_c.1.is_ok());
+        assert_eq!(JunctionError::Full, sent_c.2.unwrap_err().error());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_send_channel_capacity() {
+        Junction::builder().send_channel_capacity(0);
+    }
+}
@@ -0,0 +1,139 @@
+//! Limit on the number of `Message`s a bounded `SendChannel` may have waiting
+//! in the `Controller` of its `Junction`.
+//!
+//! Each value sent on a bounded channel first takes a `Permit` from the
+//! channel's `Capacity`. The `Permit` travels with the `Message` and gives
+//! its space back once dropped, i.e. once the `Controller` has taken the
+//! `Message` out to fire a Join Pattern with it or has discarded it.
+
+use std::sync::{Arc, Condvar, Mutex};
+
+/// Number of `Message`s that can still be sent on a bounded channel before
+/// it is full.
+pub(crate) struct Capacity {
+    free: Mutex<usize>,
+    freed: Condvar,
+}
+
+impl Capacity {
+    /// Create a `Capacity` with room for `capacity` `Message`s.
+    pub(crate) fn new(capacity: usize) -> Arc<Capacity> {
+        Arc::new(Capacity {
+            free: Mutex::new(capacity),
+            freed: Condvar::new(),
+        })
+    }
+}
+
+/// Space taken by a single `Message` of a bounded channel, given back to
+/// its `Capacity` when dropped.
+pub(crate) struct Permit {
+    capacity: Arc<Capacity>,
+}
+
+impl Permit {
+    /// Take space from the given `Capacity`, blocking the current thread
+    /// until some is available.
+    pub(crate) fn acquire(capacity: &Arc<Capacity>) -> Permit {
+        let mut free = capacity.free.lock().unwrap();
+
+        while *free == 0 {
+            free = capacity.freed.wait(free).unwrap();
+        }
+
+        *free -= 1;
+
+        Permit {
+            capacity: Arc::clone(capacity),
+        }
+    }
+
+    /// Take space from the given `Capacity` if some is available right away.
+    pub(crate) fn try_acquire(capacity: &Arc<Capacity>) -> Option<Permit> {
+        let mut free = capacity.free.lock().unwrap();
+
+        if *free == 0 {
+            return None;
+        }
+
+        *free -= 1;
+
+        Some(Permit {
+            capacity: Arc::clone(capacity),
+        })
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        // Recover from poisoning, as the count itself is never left in an
+        // inconsistent state.
+        let mut free = self
+            .capacity
+            .free
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        *free += 1;
+        self.capacity.freed.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::mpsc::channel;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_try_acquire_up_to_capacity() {
+        // Given:
+        let capacity = Capacity::new(2);
+
+        // When:
+        let first = Permit::try_acquire(&capacity);
+        let second = Permit::try_acquire(&capacity);
+
+        // Then:
+        assert!(first.is_some());
+        assert!(second.is_some());
+        assert!(Permit::try_acquire(&capacity).is_none());
+    }
+
+    #[test]
+    fn test_dropped_permit_frees_space() {
+        // Given:
+        let capacity = Capacity::new(1);
+        let permit = Permit::acquire(&capacity);
+
+        // When:
+        drop(permit);
+
+        // Then:
+        assert!(Permit::try_acquire(&capacity).is_some());
+    }
+
+    #[test]
+    fn test_acquire_blocks_until_space_is_freed() {
+        // Given:
+        let capacity = Capacity::new(1);
+        let permit = Permit::acquire(&capacity);
+        let (tx, rx) = channel::<()>();
+        let capacity_clone = Arc::clone(&capacity);
+
+        thread::spawn(move || {
+            let _permit = Permit::acquire(&capacity_clone);
+            tx.send(()).unwrap();
+        });
+
+        // When:
+        let blocked = rx.recv_timeout(Duration::from_millis(50));
+        drop(permit);
+
+        // Then:
+        assert!(blocked.is_err());
+        assert!(rx.recv().is_ok());
+    }
+}
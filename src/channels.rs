@@ -5,11 +5,19 @@
 //! a `RecvChannel` is used to get the value generated by a Join Pattern firing
 //! asynchronously.
 
+use std::future::Future;
 use std::marker::PhantomData;
-use std::sync::mpsc::{channel, RecvError, SendError, Sender};
+use std::pin::Pin;
+use std::sync::mpsc::channel;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
 use std::{any::Any, marker::Send};
 
-use super::types::{ids, Message, Packet};
+use super::capacity::{Capacity, Permit};
+use super::errors::{JunctionError, SendError};
+use super::oneshot;
+use super::types::{ids, Message, Packet, PacketSender};
 
 /***************************
  * Sending Channel Structs *
@@ -22,12 +30,16 @@ use super::types::{ids, Message, Packet};
 /// generated by Join Patterns that have been fired.
 ///
 /// Sending a message this channel will *not* block the current thread, but may
-/// allow a Join Pattern that it is part of to fire.
+/// allow a Join Pattern that it is part of to fire. Only a bounded channel
+/// blocks the sending thread, and only while it is full.
 #[derive(Clone)]
 pub struct SendChannel<T> {
     id: ids::ChannelId,
     junction_id: ids::JunctionId,
-    sender: Sender<Packet>,
+    sender: PacketSender,
+    /// Space left for messages of this channel in the Junction, shared by
+    /// all clones of the channel. `None` if the channel is unbounded.
+    capacity: Option<Arc<Capacity>>,
     send_type: PhantomData<T>,
 }
 
@@ -41,11 +53,6 @@ impl<T> SendChannel<T> {
     pub(crate) fn junction_id(&self) -> ids::JunctionId {
         self.junction_id
     }
-
-    /// Create a stripped down representation of this channel.
-    pub(crate) fn strip(&self) -> StrippedSendChannel<T> {
-        StrippedSendChannel::new(self.id)
-    }
 }
 
 impl<T> SendChannel<T>
@@ -55,50 +62,82 @@ where
     pub(crate) fn new(
         id: ids::ChannelId,
         junction_id: ids::JunctionId,
-        sender: Sender<Packet>,
+        sender: PacketSender,
     ) -> SendChannel<T> {
         SendChannel {
             id,
             junction_id,
             sender,
+            capacity: None,
             send_type: PhantomData,
         }
     }
 
-    pub fn send(&self, value: T) -> Result<(), SendError<Packet>> {
-        self.sender.send(Packet::Message {
-            channel_id: self.id,
-            msg: Message::new(value),
-        })
+    /// Limit the number of messages of this channel waiting in the Junction
+    /// to `capacity`.
+    pub(crate) fn bounded(mut self, capacity: usize) -> SendChannel<T> {
+        self.capacity = Some(Capacity::new(capacity));
+
+        self
     }
-}
 
-/// Stripped down version of `SendChannel`.
-///
-/// The main purpose of this struct is to be used in the Join Pattern types to
-/// increase readability and maintainability.
-///
-/// This version of the `SendChannel` does not carry the same functionality as
-/// the actual `SendChannel`, however, it holds the bare minimum information
-/// necessary for the creation of Join Patterns. Specifically, this channel
-/// cannot send and does not know of its Junction, but is able to provide a
-/// channel ID and type associated with it.
-pub(crate) struct StrippedSendChannel<T> {
-    id: ids::ChannelId,
-    send_type: PhantomData<T>,
-}
+    /// Send a value to the Junction.
+    ///
+    /// If the channel is bounded and has reached its capacity, block the
+    /// current thread until a Join Pattern has consumed one of its messages.
+    /// Return the value back as an error if the `Controller` of the
+    /// Junction has been stopped.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let mut msg = Message::new(value);
+
+        if let Some(capacity) = &self.capacity {
+            msg = msg.holding(Permit::acquire(capacity));
+        }
 
-impl<T> StrippedSendChannel<T> {
-    pub(crate) fn new(id: ids::ChannelId) -> StrippedSendChannel<T> {
-        StrippedSendChannel {
-            id,
-            send_type: PhantomData,
+        self.send_message(msg)
+    }
+
+    /// Send a value to the Junction without blocking.
+    ///
+    /// Return the value back as an error with `JunctionError::Full` if the
+    /// channel is bounded and has reached its capacity. Fails like `send`
+    /// in all other cases. An unbounded channel is never full.
+    pub fn try_send(&self, value: T) -> Result<(), SendError<T>> {
+        let permit = match &self.capacity {
+            Some(capacity) => match Permit::try_acquire(capacity) {
+                Some(permit) => Some(permit),
+                None => return Err(SendError::new(value, JunctionError::Full)),
+            },
+            None => None,
+        };
+
+        let mut msg = Message::new(value);
+
+        if let Some(permit) = permit {
+            msg = msg.holding(permit);
         }
+
+        self.send_message(msg)
     }
 
-    /// Return the channel's ID.
-    pub(crate) fn id(&self) -> ids::ChannelId {
-        self.id
+    /// Send the `Message` carrying a value of this channel to the Junction.
+    ///
+    /// Return the value back as an error if the `Controller` of the
+    /// Junction has been stopped.
+    fn send_message(&self, msg: Message) -> Result<(), SendError<T>> {
+        let packet = Packet::Message {
+            channel_id: self.id,
+            msg,
+        };
+
+        self.sender.send(packet).map_err(|error| match error.0 {
+            Packet::Message { msg, .. } => {
+                let value = msg.into_parts().0.unwrap().downcast::<T>().unwrap();
+
+                SendError::new(*value, JunctionError::ControllerStopped)
+            }
+            _ => unreachable!(),
+        })
     }
 }
 
@@ -118,7 +157,7 @@ impl<T> StrippedSendChannel<T> {
 pub struct RecvChannel<R> {
     id: ids::ChannelId,
     junction_id: ids::JunctionId,
-    sender: Sender<Packet>,
+    sender: PacketSender,
     recv_type: PhantomData<R>,
 }
 
@@ -132,11 +171,6 @@ impl<R> RecvChannel<R> {
     pub(crate) fn junction_id(&self) -> ids::JunctionId {
         self.junction_id
     }
-
-    /// Create a stripped down representation of this channel.
-    pub(crate) fn strip(&self) -> StrippedRecvChannel<R> {
-        StrippedRecvChannel::new(self.id)
-    }
 }
 
 impl<R> RecvChannel<R>
@@ -146,7 +180,7 @@ where
     pub(crate) fn new(
         id: ids::ChannelId,
         junction_id: ids::JunctionId,
-        sender: Sender<Packet>,
+        sender: PacketSender,
     ) -> RecvChannel<R> {
         RecvChannel {
             id,
@@ -158,49 +192,70 @@ where
 
     /// Receive value generated by fired Join Pattern.
     ///
-    /// # Panics
+    /// Return `JunctionError::ControllerStopped` if the Junction stops before
+    /// a Join Pattern fires for the request, and `JunctionError::BodyPanicked`
+    /// if the function of the fired Join Pattern panics before replying.
+    pub fn recv(&self) -> Result<R, JunctionError> {
+        self.send_return_sender(true).1.recv()
+    }
+
+    /// Receive value generated by fired Join Pattern, waiting at most for
+    /// the given timeout.
     ///
-    /// Panics if it was not possible to send a return `Sender` to the Junction.
-    pub fn recv(&self) -> Result<R, RecvError> {
-        let (tx, rx) = channel::<R>();
-
-        self.sender
-            .send(Packet::Message {
-                channel_id: self.id,
-                msg: Message::new(tx),
-            })
-            .unwrap();
-
-        rx.recv()
+    /// Once the timeout has elapsed, the request for a value is withdrawn
+    /// from the Junction, so that no Join Pattern fires for it anymore and
+    /// consumes messages of other channels in vain. Should a Join Pattern
+    /// have fired for the request in the meantime, its value is still
+    /// waited for and returned. Otherwise, `JunctionError::Timeout` is
+    /// returned. Fails like `recv` in all other cases.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<R, JunctionError> {
+        let (message_id, receiver) = self.send_return_sender(true);
+
+        recv_or_withdraw(&self.sender, self.id, message_id, receiver, timeout)
     }
-}
 
-/// Stripped down version of `RecvChannel`.
-///
-/// The main purpose of this struct is to be used in the Join Pattern types to
-/// increase readability and maintainability.
-///
-/// This version of the `RecvChannel` does not carry the same functionality as
-/// the actual `RecvChannel`, however, it holds the bare minimum information
-/// necessary for the creation of Join Patterns. Specifically, this channel
-/// cannot receive and does not know of its Junction, but is able to provide a
-/// channel ID and type associated with it.
-pub(crate) struct StrippedRecvChannel<R> {
-    id: ids::ChannelId,
-    recv_type: PhantomData<R>,
-}
+    /// Receive value generated by fired Join Pattern, only if one can fire
+    /// right away.
+    ///
+    /// If a Join Pattern this channel is part of is alive as soon as the
+    /// request for a value arrives at the Junction, the value it generates is
+    /// waited for and returned. Otherwise, `JunctionError::WouldBlock` is
+    /// returned and nothing is left behind in the Junction. Fails like
+    /// `recv` in all other cases.
+    pub fn try_recv(&self) -> Result<R, JunctionError> {
+        let (msg, receiver) = self.new_message(true);
+
+        try_send_message(&self.sender, self.id, msg, receiver)
+    }
 
-impl<R> StrippedRecvChannel<R> {
-    pub(crate) fn new(id: ids::ChannelId) -> StrippedRecvChannel<R> {
-        StrippedRecvChannel {
-            id,
-            recv_type: PhantomData,
-        }
+    /// Receive value generated by fired Join Pattern asynchronously.
+    ///
+    /// The request for a value is sent to the Junction right away, even if
+    /// the returned `ReplyFuture` is never polled. The `ReplyFuture` resolves
+    /// once a Join Pattern this channel is part of has fired and replied,
+    /// without blocking the thread polling it. It does not depend on any
+    /// particular async runtime. It fails like `recv`.
+    pub fn recv_async(&self) -> ReplyFuture<R> {
+        ReplyFuture::new(self.send_return_sender(false).1)
     }
 
-    /// Return the channel's ID.
-    pub(crate) fn id(&self) -> ids::ChannelId {
-        self.id
+    /// Send a new return `Sender` to the Junction and return its `Receiver`
+    /// alongside the ID of the `Message` carrying it.
+    ///
+    /// If `blocking` is `true`, the `Receiver` is to be waited on with
+    /// `recv`, so the function of the fired Join Pattern may be handed to it.
+    /// Should the Junction have stopped, the return `Sender` is dropped right
+    /// away, which the `Receiver` reports.
+    fn send_return_sender(&self, blocking: bool) -> (ids::MessageId, oneshot::Receiver<R>) {
+        let (msg, receiver) = self.new_message(blocking);
+
+        send_reply_message(&self.sender, self.id, msg, receiver)
+    }
+
+    /// Create a `Message` carrying a new return `Sender` and return it
+    /// alongside the `Receiver` of the `Sender`.
+    fn new_message(&self, blocking: bool) -> (Message, oneshot::Receiver<R>) {
+        new_reply_message(blocking, Message::from_return_sender)
     }
 }
 
@@ -228,7 +283,7 @@ impl<R> StrippedRecvChannel<R> {
 pub struct BidirChannel<T, R> {
     id: ids::ChannelId,
     junction_id: ids::JunctionId,
-    sender: Sender<Packet>,
+    sender: PacketSender,
     send_type: PhantomData<T>,
     recv_type: PhantomData<R>,
 }
@@ -243,11 +298,6 @@ impl<T, R> BidirChannel<T, R> {
     pub(crate) fn junction_id(&self) -> ids::JunctionId {
         self.junction_id
     }
-
-    /// Create a stripped down representation of this channel.
-    pub(crate) fn strip(&self) -> StrippedBidirChannel<T, R> {
-        StrippedBidirChannel::new(self.id)
-    }
 }
 
 impl<T, R> BidirChannel<T, R>
@@ -258,7 +308,7 @@ where
     pub(crate) fn new(
         id: ids::ChannelId,
         junction_id: ids::JunctionId,
-        sender: Sender<Packet>,
+        sender: PacketSender,
     ) -> BidirChannel<T, R> {
         BidirChannel {
             id,
@@ -271,51 +321,201 @@ where
 
     /// Send a message and receive value generated by fired Junction.
     ///
-    /// # Panics
+    /// Return `JunctionError::ControllerStopped` if the Junction stops before
+    /// a Join Pattern fires for the message, and `JunctionError::BodyPanicked`
+    /// if the function of the fired Join Pattern panics before replying. In
+    /// both cases, the message is lost.
+    pub fn send_recv(&self, msg: T) -> Result<R, JunctionError> {
+        self.send_with_return_sender(msg, true).1.recv()
+    }
+
+    /// Send a message and receive value generated by fired Junction, waiting
+    /// at most for the given timeout.
+    ///
+    /// Once the timeout has elapsed, the message is withdrawn from the
+    /// Junction, so that no Join Pattern fires for it anymore and consumes
+    /// messages of other channels in vain. Should a Join Pattern have fired
+    /// for the message in the meantime, its value is still waited for and
+    /// returned. Otherwise, `JunctionError::Timeout` is returned. Fails like
+    /// `send_recv` in all other cases.
+    pub fn send_recv_timeout(&self, msg: T, timeout: Duration) -> Result<R, JunctionError> {
+        let (message_id, receiver) = self.send_with_return_sender(msg, true);
+
+        recv_or_withdraw(&self.sender, self.id, message_id, receiver, timeout)
+    }
+
+    /// Send a message and receive value generated by fired Junction, only if
+    /// a Join Pattern can fire with the message right away.
+    ///
+    /// If a Join Pattern this channel is part of is alive as soon as the
+    /// message arrives at the Junction, the value it generates is waited for
+    /// and returned. Otherwise, `JunctionError::WouldBlock` is returned and
+    /// the message is not left behind in the Junction. Fails like
+    /// `send_recv` in all other cases.
+    pub fn try_send_recv(&self, msg: T) -> Result<R, JunctionError> {
+        let (msg, receiver) = self.new_message(msg, true);
+
+        try_send_message(&self.sender, self.id, msg, receiver)
+    }
+
+    /// Send a message and receive value generated by fired Junction
+    /// asynchronously.
     ///
-    /// Panics if it was not possible to send the given message and return
-    /// `Sender` to the Junction.
-    pub fn send_recv(&self, msg: T) -> Result<R, RecvError> {
-        let (tx, rx) = channel::<R>();
-
-        self.sender
-            .send(Packet::Message {
-                channel_id: self.id,
-                msg: Message::new((msg, tx)),
-            })
-            .unwrap();
-
-        rx.recv()
+    /// The message is sent to the Junction right away, even if the returned
+    /// `ReplyFuture` is never polled. The `ReplyFuture` resolves once a Join
+    /// Pattern this channel is part of has fired and replied, without
+    /// blocking the thread polling it. It does not depend on any particular
+    /// async runtime. It fails like `send_recv`.
+    pub fn send_recv_async(&self, msg: T) -> ReplyFuture<R> {
+        ReplyFuture::new(self.send_with_return_sender(msg, false).1)
+    }
+
+    /// Send the given message with a new return `Sender` to the Junction and
+    /// return its `Receiver` alongside the ID of the `Message` carrying both.
+    ///
+    /// If `blocking` is `true`, the `Receiver` is to be waited on with
+    /// `recv`, so the function of the fired Join Pattern may be handed to it.
+    /// Should the Junction have stopped, the return `Sender` is dropped right
+    /// away, which the `Receiver` reports.
+    fn send_with_return_sender(
+        &self,
+        msg: T,
+        blocking: bool,
+    ) -> (ids::MessageId, oneshot::Receiver<R>) {
+        let (msg, receiver) = self.new_message(msg, blocking);
+
+        send_reply_message(&self.sender, self.id, msg, receiver)
+    }
+
+    /// Create a `Message` carrying the given message and a new return
+    /// `Sender` and return it alongside the `Receiver` of the `Sender`.
+    fn new_message(&self, msg: T, blocking: bool) -> (Message, oneshot::Receiver<R>) {
+        new_reply_message(blocking, |tx| Message::with_return_sender(msg, tx))
     }
 }
 
-/// Stripped down version of `BidirChannel`.
-///
-/// The main purpose of this struct is to be used in the Join Pattern types to
-/// increase readability and maintainability.
+/// Create a `Message` from a new return `Sender` with the given function and
+/// return it alongside the `Receiver` of the `Sender`.
 ///
-/// This version of the `BidirChannel` does not carry the same functionality as
-/// the actual `BidirChannel`, however, it holds the bare minimum information
-/// necessary for the creation of Join Patterns. Specifically, this channel
-/// cannot send or receive and does not know of its Junction, but is able to
-/// provide a channel ID and type associated with it.
-pub(crate) struct StrippedBidirChannel<T, R> {
-    id: ids::ChannelId,
-    send_type: PhantomData<T>,
-    recv_type: PhantomData<R>,
+/// If `blocking` is `true`, the `Receiver` is to be waited on with `recv`, so
+/// the function of the fired Join Pattern may be handed to it.
+fn new_reply_message<R, F>(blocking: bool, into_message: F) -> (Message, oneshot::Receiver<R>)
+where
+    F: FnOnce(oneshot::Sender<R>) -> Message,
+{
+    let (tx, rx) = oneshot::channel::<R>();
+    let msg = into_message(tx);
+
+    if blocking {
+        (msg.running_jobs(), rx)
+    } else {
+        (msg, rx)
+    }
 }
 
-impl<T, R> StrippedBidirChannel<T, R> {
-    pub(crate) fn new(id: ids::ChannelId) -> StrippedBidirChannel<T, R> {
-        StrippedBidirChannel {
-            id,
-            send_type: PhantomData,
-            recv_type: PhantomData,
+/// Send the given `Message` carrying a return `Sender` to the channel with
+/// given `ChannelId` and return the ID of the `Message` alongside the given
+/// `Receiver` of the `Sender`.
+fn send_reply_message<R>(
+    sender: &PacketSender,
+    channel_id: ids::ChannelId,
+    msg: Message,
+    receiver: oneshot::Receiver<R>,
+) -> (ids::MessageId, oneshot::Receiver<R>) {
+    let message_id = msg.id().unwrap();
+
+    // A `Message` that could not be sent is dropped alongside its return
+    // `Sender`, which the `Receiver` reports as a stopped Junction.
+    let _ = sender.send(Packet::Message { channel_id, msg });
+
+    (message_id, receiver)
+}
+
+/// Send the given `Message` to the Junction, to be kept only if a Join
+/// Pattern fires with it right away, and wait for the reply if one did.
+fn try_send_message<R>(
+    sender: &PacketSender,
+    channel_id: ids::ChannelId,
+    msg: Message,
+    receiver: oneshot::Receiver<R>,
+) -> Result<R, JunctionError> {
+    let (fired_sender, fired_receiver) = channel::<bool>();
+
+    // Should the Junction have stopped, the `Packet` is dropped alongside
+    // `fired_sender`, which is reported below.
+    let _ = sender.send(Packet::TryMessage {
+        channel_id,
+        msg,
+        return_sender: fired_sender,
+    });
+
+    match fired_receiver.recv() {
+        Ok(true) => receiver.recv(),
+        Ok(false) => Err(JunctionError::WouldBlock),
+        Err(_) => Err(JunctionError::ControllerStopped),
+    }
+}
+
+/// Wait for the reply to the `Message` with given `MessageId` until the
+/// timeout elapses.
+///
+/// On timeout, request the `Message` to be withdrawn from the channel with
+/// given `ChannelId`. If a Join Pattern has consumed it already, wait for the
+/// reply after all, since the Join Pattern fired for it.
+fn recv_or_withdraw<R>(
+    sender: &PacketSender,
+    channel_id: ids::ChannelId,
+    message_id: ids::MessageId,
+    receiver: oneshot::Receiver<R>,
+    timeout: Duration,
+) -> Result<R, JunctionError> {
+    match receiver.recv_timeout(timeout) {
+        Err(JunctionError::Timeout) => {
+            let (withdrawn_sender, withdrawn_receiver) = channel::<bool>();
+
+            // A stopped Junction cannot have withdrawn the `Message`, but has
+            // dropped its return `Sender` unless a Join Pattern is running.
+            let withdrawn = sender
+                .send(Packet::WithdrawMessageRequest {
+                    channel_id,
+                    message_id,
+                    return_sender: withdrawn_sender,
+                })
+                .is_ok()
+                && withdrawn_receiver.recv().unwrap_or(false);
+
+            if withdrawn {
+                Err(JunctionError::Timeout)
+            } else {
+                receiver.recv()
+            }
         }
+        result => result,
     }
+}
 
-    /// Return the channel's ID.
-    pub(crate) fn id(&self) -> ids::ChannelId {
-        self.id
+/************************
+ * Reply Future Structs *
+ ************************/
+
+/// Future resolving to the value replied by a fired Join Pattern.
+///
+/// Returned by `RecvChannel::recv_async` and `BidirChannel::send_recv_async`.
+/// Resolves to an error if the Junction stopped before replying.
+pub struct ReplyFuture<R> {
+    receiver: oneshot::Receiver<R>,
+}
+
+impl<R> ReplyFuture<R> {
+    fn new(receiver: oneshot::Receiver<R>) -> ReplyFuture<R> {
+        ReplyFuture { receiver }
+    }
+}
+
+impl<R> Future for ReplyFuture<R> {
+    type Output = Result<R, JunctionError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.receiver).poll(cx)
     }
 }
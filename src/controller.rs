@@ -1,15 +1,40 @@
 //! Control structure started by any new `Junction`, running in a background thread
 //! to handle the coordination of Join Pattern creation and execution.
+//!
+//! With the `tokio` feature, the `Controller` can alternatively run as an
+//! async task on a tokio runtime.
 
+#[cfg(feature = "tokio")]
+use std::sync::mpsc::TryRecvError;
 use std::sync::mpsc::{Receiver, Sender};
+#[cfg(feature = "tokio")]
+use std::sync::Arc;
 use std::thread;
-use std::{cmp::Ordering, collections::HashMap, collections::LinkedList, vec::Vec};
+use std::{collections::HashMap, collections::LinkedList, vec::Vec};
+
+#[cfg(feature = "tokio")]
+use tokio::sync::Notify;
 
 use super::bag::Bag;
 use super::counter::Counter;
+use super::executors::Executor;
 use super::inverted_index::InvertedIndex;
-use super::types::ids::{ChannelId, JoinPatternId};
-use super::types::{ControllerHandle, JoinPattern, Message, Packet};
+#[cfg(feature = "tokio")]
+use super::oneshot;
+use super::patterns::JoinPattern;
+use super::policies::{Candidate, FiringPolicy};
+use super::types::functions::PanicHandler;
+use super::types::ids::{ChannelId, JoinPatternId, MessageId};
+use super::types::{ControllerHandle, FinishedGuard, Message, Packet, PacketSender};
+
+/// Number of `Packet`s a `Controller` running as an async task handles in a
+/// row before yielding to other tasks of its runtime.
+#[cfg(feature = "tokio")]
+const PACKETS_PER_YIELD: usize = 64;
+
+/// Positions of the `Message`s that a Join Pattern can be fired with, one
+/// for each of its channels in declaration order.
+type MessageIndices = Vec<usize>;
 
 /// Struct to handle `Packet`s sent from the user in the background.
 ///
@@ -18,51 +43,125 @@ use super::types::{ControllerHandle, JoinPattern, Message, Packet};
 /// `Junction` in a separate control thread, where it continuously listens
 /// for `Packet`s sent by user code and reacts accordingly.
 pub(crate) struct Controller {
-    latest_channel_id: ChannelId,
-    latest_join_pattern_id: JoinPatternId,
     /// Counter for how many messages have arrived since creation.
     message_counter: Counter,
-    /// Collection of all currently available messages.
-    messages: Bag<ChannelId, Message>,
+    /// Collection of all currently available messages, alongside the value
+    /// of `message_counter` at which each of them arrived.
+    messages: Bag<ChannelId, (Counter, Message)>,
+    /// Counter for how many Join Patterns have been fired since creation.
+    firing_counter: Counter,
     /// Collection of all available Join Patterns for the `Junction` associated with
     /// this `Controller`.
     join_patterns: HashMap<JoinPatternId, JoinPattern>,
-    /// Map of `JoinPatternId`s to the message count at which they were last
+    /// Map of `JoinPatternId`s to the firing count at which they were last
     /// fired, `None` if the Join Pattern has never been fired. Used to
     /// determine precedence of Join Patterns that have not been fired in a
     /// while when needing to choose which of the alive Join Patterns to fire.
     join_pattern_last_fired: HashMap<JoinPatternId, Option<Counter>>,
+    /// Map of `JoinPatternId`s to the number of invocations of their function
+    /// that are still running. Only kept for Join Patterns with a maximum
+    /// concurrency, which are not fired while they have reached it.
+    join_pattern_running: HashMap<JoinPatternId, usize>,
     /// `InvertedIndex` matching `ChannelId`s to all Join Patterns they appear in.
     /// Used to easily determine which Join Patterns are relevant any time a new
     /// message comes in.
     join_pattern_index: InvertedIndex<ChannelId, JoinPatternId>,
+    /// Policy to select which of the alive Join Patterns to fire.
+    firing_policy: Box<dyn FiringPolicy>,
+    /// Default `Executor` to run the functions of fired Join Patterns with.
+    executor: Box<dyn Executor>,
+    /// Handler for panics in the functions of fired Join Patterns that no
+    /// caller is waiting on.
+    panic_handler: Option<PanicHandler>,
+    /// Sender to the `Controller`'s own `Packet` channel, set once it has
+    /// been started. Used to be notified when the functions of fired Join
+    /// Patterns have finished.
+    sender: Option<PacketSender>,
 }
 
 impl Controller {
-    pub(crate) fn new() -> Controller {
+    pub(crate) fn new(
+        firing_policy: Box<dyn FiringPolicy>,
+        executor: Box<dyn Executor>,
+        panic_handler: Option<PanicHandler>,
+    ) -> Controller {
         Controller {
-            latest_channel_id: ChannelId::default(),
-            latest_join_pattern_id: JoinPatternId::default(),
             message_counter: Counter::default(),
             messages: Bag::new(),
+            firing_counter: Counter::default(),
             join_patterns: HashMap::new(),
             join_pattern_last_fired: HashMap::new(),
+            join_pattern_running: HashMap::new(),
             join_pattern_index: InvertedIndex::new(),
+            firing_policy,
+            executor,
+            panic_handler,
+            sender: None,
         }
     }
 
     /// Start thread to handle incoming `Packet`s from `Junction` user.
     ///
     /// Start new thread in the background to handle incoming `Packet`s sent from
-    /// the user of the `Junction` that created this `Controller`. Return a
-    /// `ControlThreadHandle` so that this control thread can be joint at any future
-    /// point.
+    /// the user of the `Junction` that created this `Controller`. The thread
+    /// is given `thread_name`, if any. Return a `ControlThreadHandle` so that
+    /// this control thread can be joint at any future point.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the control thread could not be spawned.
     pub(crate) fn start(
         mut self,
-        sender: Sender<Packet>,
+        sender: PacketSender,
         receiver: Receiver<Packet>,
+        thread_name: Option<String>,
     ) -> ControllerHandle {
-        ControllerHandle::new(sender, thread::spawn(move || self.handle_packets(receiver)))
+        self.sender = Some(sender.clone());
+
+        let mut builder = thread::Builder::new();
+
+        if let Some(thread_name) = thread_name {
+            builder = builder.name(thread_name);
+        }
+
+        let handle = builder
+            .spawn(move || self.handle_packets(receiver))
+            .unwrap();
+
+        ControllerHandle::new(sender, handle)
+    }
+
+    /// Start async task on the current tokio runtime to handle incoming
+    /// `Packet`s from `Junction` user.
+    ///
+    /// Works like `start`, except that no thread is spawned. The given
+    /// `PacketSender` needs to notify the task of each `Packet` it sends
+    /// through `notify`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn start_task(
+        mut self,
+        sender: PacketSender,
+        receiver: Receiver<Packet>,
+        notify: Arc<Notify>,
+    ) -> ControllerHandle {
+        let (stopped_sender, stopped_receiver) = oneshot::channel::<()>();
+
+        self.sender = Some(sender.clone());
+
+        tokio::spawn(async move {
+            self.handle_packets_async(receiver, notify).await;
+
+            // Dropping the `Controller` first releases all waiting `Message`s
+            // before the task is reported as stopped.
+            drop(self);
+            drop(stopped_sender);
+        });
+
+        ControllerHandle::from_task(sender, stopped_receiver)
     }
 
     /// Handle incoming `Packet` from associated `Junction`.
@@ -71,22 +170,79 @@ impl Controller {
     /// associated with the `Junction` that created and started this `Controller`
     /// until a `Packet::ShutDownRequest` has been sent.
     fn handle_packets(&mut self, receiver: Receiver<Packet>) {
-        use Packet::*;
-
         while let Ok(packet) = receiver.recv() {
-            match packet {
-                Message { channel_id, msg } => self.handle_message(channel_id, msg),
-                NewChannelIdRequest { return_sender } => {
-                    self.handle_new_channel_id_request(return_sender)
-                }
-                AddJoinPatternRequest { join_pattern } => {
-                    self.handle_add_join_pattern_request(join_pattern)
+            if !self.handle_packet(packet) {
+                break;
+            }
+        }
+    }
+
+    /// Handle incoming `Packet` from associated `Junction` in an async task.
+    ///
+    /// Works like `handle_packets`, except that instead of blocking while no
+    /// `Packet` is available, the task waits to be notified of new `Packet`s
+    /// through `notify`.
+    #[cfg(feature = "tokio")]
+    async fn handle_packets_async(&mut self, receiver: Receiver<Packet>, notify: Arc<Notify>) {
+        let mut handled: usize = 0;
+
+        loop {
+            match receiver.try_recv() {
+                Ok(packet) => {
+                    if !self.handle_packet(packet) {
+                        break;
+                    }
+
+                    // Receiving from the `Receiver` never yields on its own,
+                    // so give other tasks a chance to run under heavy load.
+                    handled += 1;
+
+                    if handled == PACKETS_PER_YIELD {
+                        handled = 0;
+                        tokio::task::yield_now().await;
+                    }
                 }
-                ShutDownRequest => break,
+                Err(TryRecvError::Empty) => notify.notified().await,
+                Err(TryRecvError::Disconnected) => break,
             }
         }
     }
 
+    /// Handle a single `Packet` from associated `Junction`.
+    ///
+    /// Return `false` if the `Packet` was a `Packet::ShutDownRequest`, after
+    /// which no further `Packet`s should be handled, and `true` otherwise.
+    fn handle_packet(&mut self, packet: Packet) -> bool {
+        use Packet::*;
+
+        match packet {
+            Message { channel_id, msg } => self.handle_message(channel_id, msg),
+            TryMessage {
+                channel_id,
+                msg,
+                return_sender,
+            } => self.handle_try_message(channel_id, msg, return_sender),
+            AddJoinPatternRequest {
+                join_pattern_id,
+                join_pattern,
+            } => self.handle_add_join_pattern_request(join_pattern_id, join_pattern),
+            RemoveJoinPatternRequest { join_pattern_id } => {
+                self.handle_remove_join_pattern_request(join_pattern_id)
+            }
+            WithdrawMessageRequest {
+                channel_id,
+                message_id,
+                return_sender,
+            } => self.handle_withdraw_message_request(channel_id, message_id, return_sender),
+            JoinPatternFinished { join_pattern_id } => {
+                self.handle_join_pattern_finished(join_pattern_id)
+            }
+            ShutDownRequest => return false,
+        }
+
+        true
+    }
+
     /// Handle a received `Message` from a given channel.
     ///
     /// The first action taken in handling a `Message` is storing the received
@@ -95,30 +251,221 @@ impl Controller {
     /// The second action is to start determining if any of the Join Patterns stored
     /// with the `Controller` are alive and if so, which of these to fire.
     fn handle_message(&mut self, channel_id: ChannelId, msg: Message) {
-        self.messages.add(channel_id, msg);
         self.message_counter.increment();
+        self.messages
+            .add(channel_id, (self.message_counter.clone(), msg));
 
         self.handle_join_pattern_firing(channel_id);
     }
 
-    /// Handle the firing of a `JoinPattern`, if possible.
+    /// Handle a received `Message` that is only to be kept if a Join Pattern
+    /// fires with it right away.
+    ///
+    /// The `Message` is first added as a candidate only, without counting it
+    /// as arrived. Should none of the alive Join Patterns be able to fire
+    /// with it, it is removed again before anything else happens, leaving
+    /// the `Controller` as if it had never been sent. Otherwise, one of
+    /// these Join Patterns is fired with it and firing continues as for any
+    /// other `Message`. Send back whether the `Message` has been consumed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the result could not be sent back to the requesting channel.
+    fn handle_try_message(
+        &mut self,
+        channel_id: ChannelId,
+        msg: Message,
+        return_sender: Sender<bool>,
+    ) {
+        let jp_ids: Vec<JoinPatternId> = self
+            .relevant_join_patterns(channel_id)
+            .map(|jp_ids| jp_ids.iter().copied().collect())
+            .unwrap_or_default();
+
+        let mut arrival = self.message_counter.clone();
+        arrival.increment();
+
+        self.messages.add(channel_id, (arrival.clone(), msg));
+
+        // Discarding abandoned `Message`s may shift the position of the
+        // candidate, so it is recognised by its arrival instead.
+        let consumed = self.fire_one_of(|controller| {
+            controller
+                .alive_join_patterns(&jp_ids)
+                .into_iter()
+                .filter(|(jp_id, message_indices)| {
+                    controller.join_patterns[jp_id]
+                        .channel_ids()
+                        .iter()
+                        .zip(message_indices.iter())
+                        .any(|(ch_id, &index)| {
+                            *ch_id == channel_id
+                                && controller.messages.peek_at(ch_id, index).unwrap().0 == arrival
+                        })
+                })
+                .collect()
+        });
+
+        if consumed {
+            self.message_counter = arrival;
+            self.fire_until_quiescent(&jp_ids);
+        } else {
+            self.messages
+                .retrieve_where(&channel_id, |(counter, _)| *counter == arrival);
+        }
+
+        return_sender.send(consumed).unwrap();
+    }
+
+    /// Withdraw the `Message` with given `MessageId` from a given channel.
+    ///
+    /// Send back whether the `Message` was still available, i.e. no Join
+    /// Pattern has consumed it yet. Dropping it drops its return `Sender`,
+    /// so no reply will ever be sent for it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the result could not be sent back to the requesting channel.
+    fn handle_withdraw_message_request(
+        &mut self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        return_sender: Sender<bool>,
+    ) {
+        let withdrawn = self
+            .messages
+            .retrieve_where(&channel_id, |(_, msg)| msg.id() == Some(message_id))
+            .is_some();
+
+        return_sender.send(withdrawn).unwrap();
+    }
+
+    /// Handle the firing of `JoinPattern`s, if possible.
     ///
     /// Determine which `JoinPattern`s contain the channel with the given
-    /// `ChannelId`. For these, check which ones have at least one `Message`
-    /// available for each of their channels, i.e. are alive, then select
-    /// one `JoinPattern` to be fired. If at any point during this process
-    /// no more `JoinPattern`s remain, nothing will be done.
+    /// `ChannelId` and fire them for as long as any are alive, see
+    /// `fire_until_quiescent`.
+    ///
+    /// Only these `JoinPattern`s need to be considered at first, since the
+    /// `Controller` never leaves a `JoinPattern` alive after handling a
+    /// `Packet`, so any that is alive now has the new `Message` among its
+    /// candidates.
     fn handle_join_pattern_firing(&mut self, channel_id: ChannelId) {
-        let mut alive_join_patterns: Vec<JoinPatternId> = Vec::new();
+        let jp_ids: Vec<JoinPatternId> = self
+            .relevant_join_patterns(channel_id)
+            .map(|jp_ids| jp_ids.iter().copied().collect())
+            .unwrap_or_default();
+
+        self.fire_until_quiescent(&jp_ids);
+    }
 
-        if let Some(jp_ids) = self.relevant_join_patterns(channel_id) {
-            alive_join_patterns = self.alive_join_patterns(jp_ids);
+    /// Fire the `JoinPattern`s with given `JoinPatternId`s until none is alive.
+    ///
+    /// After each firing, the remaining `Message`s may still leave some of the
+    /// `JoinPattern`s alive, so selection starts over until no more can fire.
+    ///
+    /// A firing may also bring `JoinPattern`s on other channels alive:
+    /// guards can depend on more than the values of the `Message`s, for
+    /// instance on state changed by a function run inline, and then accept
+    /// `Message`s they rejected before. Hence, once any `JoinPattern` has
+    /// fired, firing continues until none of the `JoinPattern`s of the
+    /// `Controller` are alive. State changed by functions running on other
+    /// threads is only seen when the next `Packet` is handled.
+    fn fire_until_quiescent(&mut self, join_pattern_ids: &[JoinPatternId]) {
+        if !self.fire_any_of(join_pattern_ids) {
+            return;
         }
 
-        if let Some(jp_id_to_fire) = self.select_to_fire(&mut alive_join_patterns) {
-            self.fire_join_pattern(*jp_id_to_fire);
-            self.reset_last_fired(*jp_id_to_fire);
+        while self.fire_any_of(join_pattern_ids) {}
+
+        let mut all_jp_ids: Vec<JoinPatternId> = self.join_patterns.keys().copied().collect();
+        all_jp_ids.sort_unstable();
+
+        while self.fire_any_of(&all_jp_ids) {}
+    }
+
+    /// Fire one of the `JoinPattern`s with given `JoinPatternId`s, if possible.
+    ///
+    /// Check which of the `JoinPattern`s have a suitable `Message` available
+    /// for each of their channels, i.e. are alive, then select one of these
+    /// to be fired. Return `true` if a `JoinPattern` has been fired and
+    /// `false` if none of them were alive.
+    ///
+    /// A `JoinPattern` that is only to be fired once is removed in the same
+    /// step as firing it, so that it cannot be selected again.
+    fn fire_any_of(&mut self, join_pattern_ids: &[JoinPatternId]) -> bool {
+        self.fire_one_of(|controller| controller.alive_join_patterns(join_pattern_ids))
+    }
+
+    /// Select one of the alive `JoinPattern`s returned by `find_alive` and
+    /// fire it with the `Message`s found for it.
+    ///
+    /// Should any of these `Message`s have a sender that no longer waits for
+    /// a reply, they are discarded instead, so that no `JoinPattern` consumes
+    /// other `Message`s alongside them, and selection starts over. Only the
+    /// selected `Message`s are checked, which keeps the cost of a firing
+    /// independent of the number of `Message`s waiting. Return `true` if a
+    /// `JoinPattern` has been fired and `false` if none were alive.
+    fn fire_one_of<F>(&mut self, find_alive: F) -> bool
+    where
+        F: Fn(&Controller) -> Vec<(JoinPatternId, MessageIndices)>,
+    {
+        let (jp_id_to_fire, message_indices) = loop {
+            let mut alive_join_patterns = find_alive(self);
+
+            let position = match self.select_to_fire(&alive_join_patterns) {
+                Some(position) => position,
+                None => return false,
+            };
+
+            let (jp_id, message_indices) = alive_join_patterns.swap_remove(position);
+
+            if !self.discard_abandoned_messages(jp_id, &message_indices) {
+                break (jp_id, message_indices);
+            }
+        };
+
+        self.fire_join_pattern(jp_id_to_fire, &message_indices);
+
+        if self.join_patterns[&jp_id_to_fire].is_once() {
+            self.handle_remove_join_pattern_request(jp_id_to_fire);
+        } else {
+            self.reset_last_fired(jp_id_to_fire);
         }
+
+        true
+    }
+
+    /// Discard those of the `Message`s at the given positions, chosen to fire
+    /// the `JoinPattern` with given `JoinPatternId`, whose senders no longer
+    /// wait for a reply, for instance because their thread has died or
+    /// stopped waiting.
+    ///
+    /// Return `true` if any `Message` has been discarded.
+    fn discard_abandoned_messages(
+        &mut self,
+        join_pattern_id: JoinPatternId,
+        message_indices: &[usize],
+    ) -> bool {
+        let messages = &mut self.messages;
+
+        let mut abandoned: Vec<(ChannelId, usize)> = self.join_patterns[&join_pattern_id]
+            .channel_ids()
+            .iter()
+            .copied()
+            .zip(message_indices.iter().copied())
+            .filter(|(ch_id, index)| messages.peek_at(ch_id, *index).unwrap().1.is_abandoned())
+            .collect();
+
+        // Discard from the highest position downwards, so that discarding
+        // one does not shift the positions of the others on the same channel.
+        abandoned.sort_unstable_by(|(_, a), (_, b)| b.cmp(a));
+
+        for (ch_id, index) in &abandoned {
+            messages.retrieve_at(ch_id, *index);
+        }
+
+        !abandoned.is_empty()
     }
 
     /// Return the `JoinPatternId`s of relevant Join Patterns for given `ChannelId`.
@@ -132,279 +479,293 @@ impl Controller {
     /// Return the `JoinPatternId`s of all alive `JoinPattern`s.
     ///
     /// A `JoinPattern` is considered alive if for each of the channels
-    /// involved in it, there is at least one `Message` available.
+    /// involved in it, there is a `Message` available such that together
+    /// these `Message`s satisfy the guard of the `JoinPattern`, and it has
+    /// not reached its maximum concurrency. Alongside each `JoinPatternId`,
+    /// the positions of these `Message`s are returned.
     fn alive_join_patterns(
         &self,
-        join_pattern_ids: &LinkedList<JoinPatternId>,
-    ) -> Vec<JoinPatternId> {
-        // We need clone the `JoinPatternId`s at this point to avoid
-        // because need to avoid the issue of `peek_all` borrowing mutably,
-        // but then needing to mutably borrow again later to update the
-        // latest fired `JoinPatternId`.
+        join_pattern_ids: &[JoinPatternId],
+    ) -> Vec<(JoinPatternId, MessageIndices)> {
         join_pattern_ids
             .iter()
-            .filter(|&jp_id| self.is_alive(*jp_id))
-            .cloned()
+            .filter(|&jp_id| !self.is_saturated(*jp_id))
+            .filter_map(|&jp_id| Some((jp_id, self.find_messages(jp_id)?)))
             .collect()
     }
 
+    /// Return `true` if the Join Pattern with given `JoinPatternId` has as
+    /// many invocations running as its maximum concurrency allows.
+    fn is_saturated(&self, join_pattern_id: JoinPatternId) -> bool {
+        let max_concurrency = self
+            .join_patterns
+            .get(&join_pattern_id)
+            .and_then(JoinPattern::max_concurrency);
+
+        match max_concurrency {
+            Some(max_concurrency) => {
+                self.join_pattern_running
+                    .get(&join_pattern_id)
+                    .copied()
+                    .unwrap_or(0)
+                    >= max_concurrency
+            }
+            None => false,
+        }
+    }
+
     /// Select which `JoinPattern` should be fired.
     ///
-    /// In order to avoid certain scenarious in which one `JoinPattern` would
-    /// block the execution of another, because it for instance has a subset of
-    /// the other's channels, we need to ensure that from the `JoinPattern`s
-    /// that are alive simultaneously, we select the one to be fired that has
-    /// been waiting for the longest time.
-    ///
-    /// Specifically, we record the `Counter` of the `Message` that each
-    /// `JoinPattern` has last been fired at. We use this as a pseudo-time and
-    /// simply order the `JoinPattern`s by their `Counter` values, then take
-    /// the one with the smallest.
-    ///
-    /// Note that this procedure should ensure a certain form of *fairness*,
-    /// by which if a `JoinPattern` has been alive an infinite amount of times,
-    /// it will fire at least once. In practice, this should amount to each
-    /// `JoinPattern` being incapable of getting deadlocked by others.
-    fn select_to_fire<'a>(
-        &self,
-        alive_jp_ids: &'a mut Vec<JoinPatternId>,
-    ) -> Option<&'a JoinPatternId> {
-        alive_jp_ids
-            .sort_unstable_by(|&jp_id_1, &jp_id_2| self.compare_last_fired(jp_id_1, jp_id_2));
+    /// Only the `JoinPattern`s with the highest priority among those that are
+    /// alive are considered. Between these, the `FiringPolicy` of the
+    /// `Controller` decides. Return the position of the selected
+    /// `JoinPattern` within the given ones, or `None` if none are alive.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `FiringPolicy` selects a position outside of the
+    /// `JoinPattern`s it was given.
+    fn select_to_fire(&mut self, alive_jps: &[(JoinPatternId, MessageIndices)]) -> Option<usize> {
+        let join_patterns = &self.join_patterns;
+        let last_fired = &self.join_pattern_last_fired;
+        let messages = &self.messages;
+
+        let highest_priority = alive_jps
+            .iter()
+            .map(|(jp_id, _)| join_patterns[jp_id].priority())
+            .max()?;
 
-        alive_jp_ids.first()
-    }
+        let positions: Vec<usize> = (0..alive_jps.len())
+            .filter(|&i| join_patterns[&alive_jps[i].0].priority() == highest_priority)
+            .collect();
 
-    /// Return `true` if Join Pattern with given `JoinPatternId` is alive.
-    ///
-    /// A Join Pattern is considered alive if there is at least one `Message` for
-    /// each of the channels involved in it.
-    fn is_alive(&self, join_pattern_id: JoinPatternId) -> bool {
-        use JoinPattern::*;
+        let candidates: Vec<Candidate> = positions
+            .iter()
+            .map(|&i| {
+                let (jp_id, message_indices) = &alive_jps[i];
+
+                let oldest_message = join_patterns[jp_id]
+                    .channel_ids()
+                    .iter()
+                    .zip(message_indices.iter())
+                    .map(|(ch_id, &index)| &messages.peek_at(ch_id, index).unwrap().0)
+                    .min()
+                    .unwrap();
 
-        if let Some(join_pattern) = self.join_patterns.get(&join_pattern_id) {
-            match join_pattern {
-                UnarySend(jp) => self.is_unary_alive(jp.channel_id()),
-                UnaryRecv(jp) => self.is_unary_alive(jp.channel_id()),
-                UnaryBidir(jp) => self.is_unary_alive(jp.channel_id()),
-                BinarySend(jp) => {
-                    self.is_binary_alive(jp.first_send_channel_id(), jp.second_send_channel_id())
-                }
-                BinaryRecv(jp) => self.is_binary_alive(jp.send_channel_id(), jp.recv_channel_id()),
-                BinaryBidir(jp) => {
-                    self.is_binary_alive(jp.send_channel_id(), jp.bidir_channel_id())
-                }
-                TernarySend(jp) => self.is_ternary_alive(
-                    jp.first_send_channel_id(),
-                    jp.second_send_channel_id(),
-                    jp.third_send_channel_id(),
-                ),
-                TernaryRecv(jp) => self.is_ternary_alive(
-                    jp.first_send_channel_id(),
-                    jp.second_send_channel_id(),
-                    jp.recv_channel_id(),
-                ),
-                TernaryBidir(jp) => self.is_ternary_alive(
-                    jp.first_send_channel_id(),
-                    jp.second_send_channel_id(),
-                    jp.bidir_channel_id(),
-                ),
-            }
-        } else {
-            false
-        }
-    }
+                Candidate::new(*jp_id, &last_fired[jp_id], oldest_message)
+            })
+            .collect();
 
-    /// Return `true` if *unary* Join Pattern with given `JoinPatternId` is alive.
-    ///
-    /// A Join Pattern is considered alive if there is at least one `Message` for
-    /// each of the channels involved in it.
-    fn is_unary_alive(&self, channel_id: ChannelId) -> bool {
-        self.messages.count_items(&channel_id) >= 1
+        Some(positions[self.firing_policy.select(&candidates)])
     }
 
-    /// Return `true` if *binary* Join Pattern with given `JoinPatternId` is alive.
+    /// Find the `Message`s to fire the Join Pattern with given `JoinPatternId`.
     ///
-    /// A Join Pattern is considered alive if there is at least one `Message` for
-    /// each of the channels involved in it.
+    /// Return the positions of the `Message`s, one for each channel of the
+    /// Join Pattern in declaration order, if the Join Pattern is alive.
+    /// Otherwise, return `None`.
     ///
-    /// For binary Join Patterns, we need to ensure that should both channels
-    /// involved have the same `ChannelId`, we actually have at least two
-    /// `Message`s available.
-    fn is_binary_alive(&self, first_ch_id: ChannelId, second_ch_id: ChannelId) -> bool {
-        if first_ch_id == second_ch_id {
-            self.messages.count_items(&first_ch_id) >= 2
+    /// Combinations of `Message`s are considered with the oldest `Message`s
+    /// first and the first one satisfying the guard of the Join Pattern is
+    /// chosen. Without a guard, this is simply the oldest `Message` for each
+    /// channel. Should the same channel appear multiple times in a Join
+    /// Pattern, each occurrence is assigned a different `Message`.
+    fn find_messages(&self, join_pattern_id: JoinPatternId) -> Option<MessageIndices> {
+        let join_pattern = self.join_patterns.get(&join_pattern_id)?;
+        let channel_ids = join_pattern.channel_ids();
+
+        let has_enough_messages = channel_ids.iter().all(|ch_id| {
+            let required = channel_ids.iter().filter(|&id| id == ch_id).count();
+
+            self.messages.count_items(ch_id) >= required
+        });
+
+        if !has_enough_messages {
+            return None;
+        }
+
+        let mut message_indices = Vec::with_capacity(channel_ids.len());
+
+        if self.search_messages(join_pattern, &mut message_indices) {
+            Some(message_indices)
         } else {
-            self.is_unary_alive(first_ch_id) && self.is_unary_alive(second_ch_id)
+            None
         }
     }
 
-    /// Return `true` if *ternary* Join Pattern with given `JoinPatternId` is alive.
+    /// Search for `Message`s satisfying the guard of the given Join Pattern.
     ///
-    /// A Join Pattern is considered alive if there is at least one `Message` for
-    /// each of the channels involved in it.
+    /// Extend the given positions of `Message`s chosen for the first channels
+    /// of the Join Pattern by positions for the remaining channels. Return
+    /// `true` if this was possible, leaving the full list of positions in
+    /// `message_indices`, and `false` otherwise.
     ///
-    /// For ternary Join Patterns, we need to ensure that should more than one
-    /// channel involved have the same `ChannelId`, we actually have enough
-    /// `Message`s available.
-    fn is_ternary_alive(
+    /// The search backtracks over every combination of `Message`s until one
+    /// satisfies the guard, so the guard may be evaluated once per
+    /// combination, i.e. exponentially often in the number of channels.
+    fn search_messages(
         &self,
-        first_ch_id: ChannelId,
-        second_ch_id: ChannelId,
-        third_ch_id: ChannelId,
+        join_pattern: &JoinPattern,
+        message_indices: &mut MessageIndices,
     ) -> bool {
-        if first_ch_id == second_ch_id && second_ch_id == third_ch_id {
-            self.messages.count_items(&first_ch_id) >= 3
-        } else {
-            self.is_binary_alive(first_ch_id, second_ch_id)
-                && self.is_binary_alive(first_ch_id, third_ch_id)
-                && self.is_binary_alive(second_ch_id, third_ch_id)
+        let channel_ids = join_pattern.channel_ids();
+        let position = message_indices.len();
+
+        if position == channel_ids.len() {
+            let messages: Vec<&Message> = channel_ids
+                .iter()
+                .zip(message_indices.iter())
+                .map(|(ch_id, &index)| &self.messages.peek_at(ch_id, index).unwrap().1)
+                .collect();
+
+            return join_pattern.is_satisfied_by(&messages);
         }
-    }
 
-    /// Compare when the Join Patterns with given `JoinPatternId`s were last alive at.
-    ///
-    /// Rules for Order:
-    /// 1. If neither `JoinPatternId` has a last alive `Counter`, then neither
-    /// has been fired yet, so they can be viewed as equal in this ordering.
-    /// 2. If only one `JoinPatternId` has no last alive `Counter`, then that
-    /// one has to be ordered as less than the other since having been fired
-    /// at least once will always be a later point of firing than not having
-    /// been fired yet.
-    /// 3. If both `JoinPatternId`s have last alive `Counter`s, use the ordering
-    /// of these.
-    ///
-    /// # Panics
-    ///
-    /// For simplicity, this function panics if the either of the given
-    /// `JoinPatternId`s is not registered in the internal map of `JoinPatternId`s
-    /// to `Instant`s that describe the last `Instant` at which a particular Join
-    /// Pattern was alive. That is to say, this function should only be called on
-    /// `JoinPatternId`s which are definitely stored in the calling `Controller`.
-    fn compare_last_fired(&self, jp_id_1: JoinPatternId, jp_id_2: JoinPatternId) -> Ordering {
-        // TODO: Can we sensibly use `Option::flatten` here?
-        let last_fired_1 = self.join_pattern_last_fired.get(&jp_id_1).unwrap();
-        let last_fired_2 = self.join_pattern_last_fired.get(&jp_id_2).unwrap();
-
-        if last_fired_1.is_none() && last_fired_2.is_none() {
-            Ordering::Equal
-        } else if last_fired_1.is_none() && last_fired_2.is_some() {
-            Ordering::Less
-        } else if last_fired_1.is_some() && last_fired_2.is_none() {
-            Ordering::Greater
-        } else {
-            last_fired_1.cmp(last_fired_2)
+        let ch_id = channel_ids[position];
+
+        for index in 0..self.messages.count_items(&ch_id) {
+            // Each `Message` can only be used for a single channel.
+            let is_taken = channel_ids[..position]
+                .iter()
+                .zip(message_indices.iter())
+                .any(|(&other_ch_id, &other_index)| other_ch_id == ch_id && other_index == index);
+
+            if is_taken {
+                continue;
+            }
+
+            message_indices.push(index);
+
+            if self.search_messages(join_pattern, message_indices) {
+                return true;
+            }
+
+            message_indices.pop();
         }
+
+        false
     }
 
     /// Fire the `JoinPattern` corresponding to the given `JoinPatternId`.
     ///
     /// The processs of firing a `JoinPattern` consists of first retrieving
-    /// a `Message` for each of the channels involved in the `JoinPattern`,
-    /// then passing these `Messages`s to the `JoinPattern` to handle the
-    /// firing.
+    /// the `Message`s at the given positions for each of the channels involved
+    /// in the `JoinPattern`, then passing these `Messages`s to the
+    /// `JoinPattern` to handle the firing.
+    ///
+    /// The space the `Message`s take up in bounded channels is freed right
+    /// away rather than once the function of the `JoinPattern` runs, which
+    /// may have to wait for an earlier function that sends on one of these
+    /// channels.
+    ///
+    /// If the `JoinPattern` has a maximum concurrency, it is counted as
+    /// running until the `Controller` is notified that its function has
+    /// finished.
     ///
     /// # Panics
     ///
     /// Panics when there is no `JoinPattern` stored for the given
-    /// `JoinPatternId`.
-    fn fire_join_pattern(&mut self, join_pattern_id: JoinPatternId) {
-        use JoinPattern::*;
-
+    /// `JoinPatternId` or no `Message` at one of the given positions.
+    fn fire_join_pattern(&mut self, join_pattern_id: JoinPatternId, message_indices: &[usize]) {
         let join_pattern = self.join_patterns.get(&join_pattern_id).unwrap();
+        let channel_ids = join_pattern.channel_ids();
 
-        match join_pattern {
-            UnarySend(jp) => {
-                let arg = self.messages.retrieve(&jp.channel_id()).unwrap();
-
-                jp.fire(arg);
-            }
-            UnaryRecv(jp) => {
-                let return_sender = self.messages.retrieve(&jp.channel_id()).unwrap();
-
-                jp.fire(return_sender);
-            }
-            UnaryBidir(jp) => {
-                let arg_and_sender = self.messages.retrieve(&jp.channel_id()).unwrap();
-
-                jp.fire(arg_and_sender);
-            }
-            BinarySend(jp) => {
-                let arg_1 = self.messages.retrieve(&jp.first_send_channel_id()).unwrap();
-                let arg_2 = self
-                    .messages
-                    .retrieve(&jp.second_send_channel_id())
-                    .unwrap();
-
-                jp.fire(arg_1, arg_2);
-            }
-            BinaryRecv(jp) => {
-                let arg = self.messages.retrieve(&jp.send_channel_id()).unwrap();
-                let return_sender = self.messages.retrieve(&jp.recv_channel_id()).unwrap();
+        // Retrieve `Message`s from the highest position downwards, so that
+        // retrieving one does not shift the positions of those still to be
+        // retrieved for the same channel.
+        let mut order: Vec<usize> = (0..channel_ids.len()).collect();
+        order.sort_unstable_by(|&a, &b| message_indices[b].cmp(&message_indices[a]));
 
-                jp.fire(arg, return_sender);
-            }
-            BinaryBidir(jp) => {
-                let arg_1 = self.messages.retrieve(&jp.send_channel_id()).unwrap();
-                let arg_2_and_sender = self.messages.retrieve(&jp.bidir_channel_id()).unwrap();
+        let mut messages: Vec<Option<Message>> = channel_ids.iter().map(|_| None).collect();
 
-                jp.fire(arg_1, arg_2_and_sender);
-            }
-            TernarySend(jp) => {
-                let arg_1 = self.messages.retrieve(&jp.first_send_channel_id()).unwrap();
-                let arg_2 = self
-                    .messages
-                    .retrieve(&jp.second_send_channel_id())
-                    .unwrap();
-                let arg_3 = self.messages.retrieve(&jp.third_send_channel_id()).unwrap();
+        for position in order {
+            messages[position] = self
+                .messages
+                .retrieve_at(&channel_ids[position], message_indices[position])
+                .map(|(_, mut msg)| {
+                    msg.release_permit();
 
-                jp.fire(arg_1, arg_2, arg_3);
-            }
-            TernaryRecv(jp) => {
-                let arg_1 = self.messages.retrieve(&jp.first_send_channel_id()).unwrap();
-                let arg_2 = self
-                    .messages
-                    .retrieve(&jp.second_send_channel_id())
-                    .unwrap();
-                let return_sender = self.messages.retrieve(&jp.recv_channel_id()).unwrap();
+                    msg
+                });
+        }
 
-                jp.fire(arg_1, arg_2, return_sender);
-            }
-            TernaryBidir(jp) => {
-                let arg_1 = self.messages.retrieve(&jp.first_send_channel_id()).unwrap();
-                let arg_2 = self
-                    .messages
-                    .retrieve(&jp.second_send_channel_id())
-                    .unwrap();
-                let arg_3_and_sender = self.messages.retrieve(&jp.bidir_channel_id()).unwrap();
+        let finished = if join_pattern.max_concurrency().is_some() {
+            *self
+                .join_pattern_running
+                .entry(join_pattern_id)
+                .or_insert(0) += 1;
 
-                jp.fire(arg_1, arg_2, arg_3_and_sender);
-            }
-        }
+            self.sender
+                .clone()
+                .map(|sender| FinishedGuard::new(join_pattern_id, sender))
+        } else {
+            None
+        };
+
+        join_pattern.fire(
+            messages.into_iter().map(Option::unwrap).collect(),
+            self.executor.as_ref(),
+            self.panic_handler.as_ref(),
+            finished,
+        );
     }
 
     /// Reset the `Counter` at which the given Join Pattern has last been fired.
+    ///
+    /// Each firing is counted separately, so that of the Join Patterns fired
+    /// while handling the same `Packet`, the one fired first also counts as
+    /// the less recently fired.
     fn reset_last_fired(&mut self, join_pattern_id: JoinPatternId) {
+        self.firing_counter.increment();
         self.join_pattern_last_fired
-            .insert(join_pattern_id, Some(self.message_counter.clone()));
+            .insert(join_pattern_id, Some(self.firing_counter.clone()));
     }
 
-    /// Send new, *unique* `ChannelId` back to the requesting `Junction`.
-    ///
-    /// # Panics
+    /// Add new Join Pattern to `Controller` storage.
     ///
-    /// Panics if the new `ChannelId` could not be sent to the requesting `Junction`.
-    fn handle_new_channel_id_request(&mut self, return_sender: Sender<ChannelId>) {
-        return_sender.send(self.new_channel_id()).unwrap();
+    /// Then fire the new Join Pattern as often as the `Message`s that arrived
+    /// before it was added allow.
+    fn handle_add_join_pattern_request(
+        &mut self,
+        join_pattern_id: JoinPatternId,
+        join_pattern: JoinPattern,
+    ) {
+        self.initialize_last_fired(join_pattern_id);
+
+        self.insert_join_pattern(join_pattern_id, join_pattern);
+
+        self.fire_until_quiescent(&[join_pattern_id]);
     }
 
-    /// Add new Join Pattern to `Controller` storage.
-    fn handle_add_join_pattern_request(&mut self, join_pattern: JoinPattern) {
-        let jp_id = self.new_join_pattern_id();
+    /// Remove Join Pattern from all internal storage of the `Controller`.
+    ///
+    /// Requests to remove Join Patterns that are not stored with the
+    /// `Controller`, for instance because they have already been removed,
+    /// are ignored.
+    fn handle_remove_join_pattern_request(&mut self, join_pattern_id: JoinPatternId) {
+        if let Some(join_pattern) = self.join_patterns.remove(&join_pattern_id) {
+            for ch_id in join_pattern.channel_ids() {
+                self.join_pattern_index
+                    .remove_single(ch_id, &join_pattern_id);
+            }
 
-        self.initialize_last_fired(jp_id);
+            self.join_pattern_last_fired.remove(&join_pattern_id);
+            self.join_pattern_running.remove(&join_pattern_id);
+        }
+    }
 
-        self.insert_join_pattern(jp_id, join_pattern);
+    /// Handle the function of a fired Join Pattern having finished.
+    ///
+    /// If the Join Pattern has a maximum concurrency, one less of its
+    /// invocations is running now, which may leave it alive again with the
+    /// `Message`s that arrived in the meantime. It is then fired as often as
+    /// these allow.
+    fn handle_join_pattern_finished(&mut self, join_pattern_id: JoinPatternId) {
+        if let Some(running) = self.join_pattern_running.get_mut(&join_pattern_id) {
+            *running = running.saturating_sub(1);
+
+            self.fire_until_quiescent(&[join_pattern_id]);
+        }
     }
 
     /// Initialize the `Instant` at which Join Pattern was last alive.
@@ -418,97 +779,17 @@ impl Controller {
     /// `InvertedIndex` for future look-up operations and then stored in the
     /// Join Pattern collection.
     fn insert_join_pattern(&mut self, join_pattern_id: JoinPatternId, join_pattern: JoinPattern) {
-        use JoinPattern::*;
+        let channel_ids = join_pattern.channel_ids();
 
-        match join_pattern {
-            UnarySend(jp) => {
-                self.join_pattern_index
-                    .insert_single(jp.channel_id(), join_pattern_id);
-
-                self.join_patterns.insert(join_pattern_id, UnarySend(jp));
-            }
-            UnaryRecv(jp) => {
+        // Register each channel only once, even if it appears multiple times
+        // within the Join Pattern.
+        for (i, ch_id) in channel_ids.iter().enumerate() {
+            if !channel_ids[..i].contains(ch_id) {
                 self.join_pattern_index
-                    .insert_single(jp.channel_id(), join_pattern_id);
-
-                self.join_patterns.insert(join_pattern_id, UnaryRecv(jp));
-            }
-            UnaryBidir(jp) => {
-                self.join_pattern_index
-                    .insert_single(jp.channel_id(), join_pattern_id);
-
-                self.join_patterns.insert(join_pattern_id, UnaryBidir(jp));
-            }
-            BinarySend(jp) => {
-                self.join_pattern_index
-                    .insert_single(jp.first_send_channel_id(), join_pattern_id);
-                self.join_pattern_index
-                    .insert_single(jp.second_send_channel_id(), join_pattern_id);
-
-                self.join_patterns.insert(join_pattern_id, BinarySend(jp));
-            }
-            BinaryRecv(jp) => {
-                self.join_pattern_index
-                    .insert_single(jp.send_channel_id(), join_pattern_id);
-                self.join_pattern_index
-                    .insert_single(jp.recv_channel_id(), join_pattern_id);
-
-                self.join_patterns.insert(join_pattern_id, BinaryRecv(jp));
-            }
-            BinaryBidir(jp) => {
-                self.join_pattern_index
-                    .insert_single(jp.send_channel_id(), join_pattern_id);
-                self.join_pattern_index
-                    .insert_single(jp.bidir_channel_id(), join_pattern_id);
-
-                self.join_patterns.insert(join_pattern_id, BinaryBidir(jp));
-            }
-            TernarySend(jp) => {
-                self.join_pattern_index
-                    .insert_single(jp.first_send_channel_id(), join_pattern_id);
-                self.join_pattern_index
-                    .insert_single(jp.second_send_channel_id(), join_pattern_id);
-                self.join_pattern_index
-                    .insert_single(jp.third_send_channel_id(), join_pattern_id);
-
-                self.join_patterns.insert(join_pattern_id, TernarySend(jp));
-            }
-            TernaryRecv(jp) => {
-                self.join_pattern_index
-                    .insert_single(jp.first_send_channel_id(), join_pattern_id);
-                self.join_pattern_index
-                    .insert_single(jp.second_send_channel_id(), join_pattern_id);
-                self.join_pattern_index
-                    .insert_single(jp.recv_channel_id(), join_pattern_id);
-
-                self.join_patterns.insert(join_pattern_id, TernaryRecv(jp));
-            }
-            TernaryBidir(jp) => {
-                self.join_pattern_index
-                    .insert_single(jp.first_send_channel_id(), join_pattern_id);
-                self.join_pattern_index
-                    .insert_single(jp.second_send_channel_id(), join_pattern_id);
-                self.join_pattern_index
-                    .insert_single(jp.bidir_channel_id(), join_pattern_id);
-
-                self.join_patterns.insert(join_pattern_id, TernaryBidir(jp));
+                    .insert_single(*ch_id, join_pattern_id);
             }
         }
-    }
-
-    /// Generate new, *unique* `ChannelId`.
-    fn new_channel_id(&mut self) -> ChannelId {
-        let ch_id = self.latest_channel_id;
-        self.latest_channel_id.increment();
-
-        ch_id
-    }
-
-    /// Generate new, *unique* `JoinPatternId`.
-    fn new_join_pattern_id(&mut self) -> JoinPatternId {
-        let jp_id = self.latest_join_pattern_id;
-        self.latest_join_pattern_id.increment();
 
-        jp_id
+        self.join_patterns.insert(join_pattern_id, join_pattern);
     }
 }
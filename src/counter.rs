@@ -13,14 +13,14 @@ use std::vec::Vec;
 /// Type alias for unsigned integer type used. Makes switching trivial.
 ///
 /// All that is required for the unsigned integer type is that is provides
-/// a `max_value` and a `min_value` function.
+/// a `MAX` and a `MIN` constant.
 type Uint = u64;
 
 /// Minimum value of used unsigned integer type.
-const UINT_MIN: Uint = Uint::min_value();
+const UINT_MIN: Uint = Uint::MIN;
 
 /// Maximum value of used unsigned integer type.
-const UINT_MAX: Uint = Uint::max_value();
+const UINT_MAX: Uint = Uint::MAX;
 
 /// Constant for the value of a carry variable when no carry occurs.
 const NO_CARRY: Uint = 0;
@@ -83,10 +83,9 @@ impl Default for Counter {
     /// any given `Counter` exceeds the maximum possible value and so we aim
     /// to conserve space initially.
     fn default() -> Self {
-        let mut digits = Vec::with_capacity(1);
-        digits.push(UINT_MIN);
-
-        Counter { digits }
+        Counter {
+            digits: vec![UINT_MIN],
+        }
     }
 }
 
@@ -123,13 +122,13 @@ impl Ord for Counter {
     ///
     /// The rules for ordering are as follows:
     /// 1. If the first `Counter`'s digits are fewer, it is less, since the
-    /// more digits the greater the counter value.
+    ///    more digits the greater the counter value.
     /// 2. If the first `Counter`'s digits are more, it is greater, since the
-    /// more digits the greater the counter value.
+    ///    more digits the greater the counter value.
     /// 3. If both `Counter`s have an equal number of digits, then compare each
-    /// digit from left (lowest index) to right (greatest index) and choose the
-    /// last Ordering that is not `Ordering::Equal`. If there is `None`, then
-    /// the two `Counter`s are equal.
+    ///    digit from left (lowest index) to right (greatest index) and choose the
+    ///    last Ordering that is not `Ordering::Equal`. If there is `None`, then
+    ///    the two `Counter`s are equal.
     fn cmp(&self, other: &Self) -> Ordering {
         if self.digits.len() < other.digits.len() {
             Ordering::Less
@@ -141,8 +140,7 @@ impl Ord for Counter {
                 .iter()
                 .zip(other.digits.iter())
                 .map(|(a, b)| a.cmp(b))
-                .filter(|&v| v != Ordering::Equal)
-                .last();
+                .rfind(|&v| v != Ordering::Equal);
 
             match comparison {
                 Some(cmp) => cmp,
@@ -329,6 +327,8 @@ mod tests {
         }
     }
 
+    // Negated comparisons are asserted on purpose, to test each operator.
+    #[allow(clippy::nonminimal_bool)]
     mod ordering {
         use super::*;
 
@@ -0,0 +1,160 @@
+//! Errors returned by the channels and `Junction`s of this crate.
+//!
+//! Operations waiting for the reply of a Join Pattern fail with a
+//! `JunctionError` describing why no reply arrived. Sending a value on a
+//! `SendChannel` fails with a `SendError`, which hands the value back.
+
+use std::error::Error;
+use std::fmt;
+
+/// Reason why an operation on a `Junction` or one of its channels failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JunctionError {
+    /// The `Controller` of the `Junction` has been stopped, so no Join
+    /// Pattern will fire anymore.
+    ControllerStopped,
+    /// The function of the fired Join Pattern panicked before replying,
+    /// with the given panic message.
+    BodyPanicked(String),
+    /// No Join Pattern fired before the timeout elapsed.
+    Timeout,
+    /// No Join Pattern could fire right away.
+    WouldBlock,
+    /// The bounded channel has no space left for another message.
+    Full,
+    /// The channel has not been created by the `Junction` or partial Join
+    /// Pattern it was used with.
+    WrongJunction,
+}
+
+impl fmt::Display for JunctionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JunctionError::ControllerStopped => write!(f, "Junction has been stopped"),
+            JunctionError::BodyPanicked(message) => {
+                write!(f, "Join Pattern panicked before replying: {}", message)
+            }
+            JunctionError::Timeout => write!(f, "no Join Pattern fired before the timeout"),
+            JunctionError::WouldBlock => write!(f, "no Join Pattern could fire right away"),
+            JunctionError::Full => write!(f, "channel has reached its capacity"),
+            JunctionError::WrongJunction => {
+                write!(f, "channel is not associated with the same Junction")
+            }
+        }
+    }
+}
+
+impl Error for JunctionError {}
+
+/// Error returned when a value could not be sent to a `Junction`.
+///
+/// Carries the value that could not be sent, so that it is not lost.
+pub struct SendError<T> {
+    value: T,
+    error: JunctionError,
+}
+
+impl<T> SendError<T> {
+    pub(crate) fn new(value: T, error: JunctionError) -> SendError<T> {
+        SendError { value, error }
+    }
+
+    /// Return the reason why the value could not be sent.
+    pub fn error(&self) -> JunctionError {
+        self.error.clone()
+    }
+
+    /// Return the value that could not be sent.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendError")
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.error {
+            JunctionError::ControllerStopped => write!(f, "sending on a closed channel"),
+            JunctionError::Full => write!(f, "sending on a full channel"),
+            ref error => write!(f, "sending failed: {}", error),
+        }
+    }
+}
+
+impl<T> Error for SendError<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_send_error_returns_value() {
+        // Given:
+        let error = SendError::new(String::from("unsent"), JunctionError::ControllerStopped);
+
+        // Then:
+        assert_eq!(JunctionError::ControllerStopped, error.error());
+        assert_eq!("unsent", error.into_inner());
+    }
+
+    #[test]
+    fn test_body_panicked_displays_message() {
+        // Given:
+        let error = JunctionError::BodyPanicked(String::from("out of stock"));
+
+        // Then:
+        assert_eq!(
+            "Join Pattern panicked before replying: out of stock",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn test_send_error_displays_closed_channel() {
+        // Given:
+        let error = SendError::new(42, JunctionError::ControllerStopped);
+
+        // Then:
+        assert_eq!("sending on a closed channel", error.to_string());
+    }
+
+    #[test]
+    fn test_send_error_displays_full_channel() {
+        // Given:
+        let error = SendError::new(42, JunctionError::Full);
+
+        // Then:
+        assert_eq!("sending on a full channel", error.to_string());
+    }
+
+    #[test]
+    fn test_send_error_displays_other_errors() {
+        // Given:
+        let error = SendError::new(42, JunctionError::WrongJunction);
+
+        // Then:
+        assert_eq!(
+            "sending failed: channel is not associated with the same Junction",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn test_send_error_debug_hides_value() {
+        // Given:
+        let error = SendError::new(42, JunctionError::ControllerStopped);
+
+        // Then:
+        assert_eq!(
+            "SendError { error: ControllerStopped, .. }",
+            format!("{:?}", error)
+        );
+    }
+}
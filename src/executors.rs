@@ -0,0 +1,137 @@
+//! Executors running the functions of fired Join Patterns.
+//!
+//! Whenever a Join Pattern fires, its function is handed to an `Executor`
+//! as a `Job`. Each `Junction` has an `Executor` used for all of its Join
+//! Patterns, which by default is a `ThreadPool` owned by the `Junction`.
+//! Individual Join Patterns can override it with an `Executor` of their own.
+//!
+//! Join Patterns with asynchronous functions additionally need a `Spawner`
+//! to run the `Future`s their functions return.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::thread;
+
+pub use super::pool::ThreadPool;
+
+/// Function of a fired Join Pattern, applied to its `Message`s.
+pub type Job = Box<dyn FnOnce() + Send>;
+
+/// Strategy to run the `Job`s of fired Join Patterns.
+///
+/// `Job`s are handed to the `Executor` from the control thread of the
+/// `Junction`, so `execute` should return quickly. It may however run the
+/// `Job` right away, which blocks the `Junction` until the `Job` is done.
+pub trait Executor: Send + Sync {
+    /// Run the given `Job`.
+    fn execute(&self, job: Job);
+}
+
+impl<E> Executor for Arc<E>
+where
+    E: Executor + ?Sized,
+{
+    fn execute(&self, job: Job) {
+        (**self).execute(job)
+    }
+}
+
+impl<E> Executor for Box<E>
+where
+    E: Executor + ?Sized,
+{
+    fn execute(&self, job: Job) {
+        (**self).execute(job)
+    }
+}
+
+/// `Future` of an asynchronous Join Pattern function, replying on completion.
+pub type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Strategy to run the `Future`s of asynchronous Join Pattern functions.
+///
+/// Usually hands the `Future` to an async runtime, for instance with a
+/// closure like `|future| { tokio::spawn(future); }`, which implements this
+/// trait.
+pub trait Spawner: Send + Sync {
+    /// Run the given `Future` to completion.
+    fn spawn(&self, future: BoxFuture);
+}
+
+impl<F> Spawner for F
+where
+    F: Fn(BoxFuture) + Send + Sync,
+{
+    fn spawn(&self, future: BoxFuture) {
+        self(future)
+    }
+}
+
+/// Run each `Job` on a newly spawned thread.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThreadPerJob;
+
+impl Executor for ThreadPerJob {
+    fn execute(&self, job: Job) {
+        thread::spawn(job);
+    }
+}
+
+/// Run each `Job` directly on the control thread of the `Junction`.
+///
+/// Only suitable for short functions that never block. While a `Job` runs,
+/// the `Junction` cannot handle any other messages, so a function waiting
+/// on a synchronous channel of its own `Junction` blocks it forever.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Inline;
+
+impl Executor for Inline {
+    fn execute(&self, job: Job) {
+        job();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn test_thread_per_job_runs_on_other_thread() {
+        // Given:
+        let (tx, rx) = channel::<thread::ThreadId>();
+
+        // When:
+        ThreadPerJob.execute(Box::new(move || tx.send(thread::current().id()).unwrap()));
+
+        // Then:
+        assert_ne!(thread::current().id(), rx.recv().unwrap());
+    }
+
+    #[test]
+    fn test_inline_runs_on_calling_thread() {
+        // Given:
+        let (tx, rx) = channel::<thread::ThreadId>();
+
+        // When:
+        Inline.execute(Box::new(move || tx.send(thread::current().id()).unwrap()));
+
+        // Then:
+        assert_eq!(thread::current().id(), rx.try_recv().unwrap());
+    }
+
+    #[test]
+    fn test_shared_executor() {
+        // Given:
+        let executor: Arc<dyn Executor> = Arc::new(Inline);
+        let (tx, rx) = channel::<i32>();
+
+        // When:
+        executor.execute(Box::new(move || tx.send(1729).unwrap()));
+
+        // Then:
+        assert_eq!(1729, rx.try_recv().unwrap());
+    }
+}
@@ -1,163 +1,164 @@
 //! Function transformers used to hide actual type signatures of functions stored
 //! with a Join Pattern and instead expose a generic interface that is easily stored.
 
-use std::any::Any;
-use std::sync::mpsc::Sender;
-
-use crate::types::{functions, Message};
-
-/// Function transformers for functions stored with unary Join Patterns.
-pub(crate) mod unary {
-    use super::*;
-
-    /// Transform function of `SendJoinPattern` to use `Message` arguments.
-    pub(crate) fn transform_send<F, T>(f: F) -> Box<impl functions::unary::FnBoxClone>
-    where
-        F: Fn(T) -> () + Send + Clone + 'static,
-        T: Any + Send + 'static,
-    {
-        Box::new(move |arg: Message| {
-            f(*arg.downcast::<T>().unwrap());
-        })
-    }
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use crate::errors::JunctionError;
+use crate::executors::Spawner;
+use crate::types::functions::{self, PanicHandler};
+use crate::types::tuples::{FromValues, Replies};
+use crate::types::{Message, Payload};
+
+/// Transform function of a Join Pattern to use `Message` arguments.
+///
+/// The given function takes a tuple of the values sent on the channels of the
+/// Join Pattern, in declaration order, and returns the replies for all of its
+/// synchronous channels. The transformed function takes one `Message` for each
+/// channel instead, then sends each reply through the return `Sender` of the
+/// synchronous channel it belongs to.
+///
+/// Should the given function panic, the panic is caught and reported with
+/// `report_panic` instead of unwinding any further.
+pub(crate) fn transform<F, A, Rs>(f: F) -> Box<impl functions::FnBoxClone>
+where
+    F: Fn(A) -> Rs::Output + Send + Clone + 'static,
+    A: FromValues,
+    Rs: Replies,
+{
+    Box::new(
+        move |messages: Vec<Message>, panic_handler: Option<PanicHandler>| {
+            let (values, return_senders) = split_messages(messages);
+
+            match panic::catch_unwind(AssertUnwindSafe(|| f(A::from_values(values)))) {
+                Ok(output) => Rs::send_replies(output, return_senders),
+                Err(payload) => report_panic::<Rs>(payload, return_senders, panic_handler),
+            }
+        },
+    )
+}
 
-    /// Transform function of `RecvJoinPattern` to use `Message` arguments.
-    pub(crate) fn transform_recv<F, R>(f: F) -> Box<impl functions::unary::FnBoxClone>
-    where
-        F: Fn() -> R + Send + Clone + 'static,
-        R: Any + Send + 'static,
-    {
-        Box::new(move |return_sender: Message| {
-            let return_sender = *return_sender.downcast::<Sender<R>>().unwrap();
-
-            return_sender.send(f()).unwrap();
-        })
-    }
+/// Transform asynchronous function of a Join Pattern to use `Message` arguments.
+///
+/// Works like `transform`, except that the given function returns a `Future`
+/// resolving to the replies. The transformed function hands this `Future`
+/// to the given `Spawner` and sends the replies once it has completed.
+/// Panics while creating or polling the `Future` are reported alike.
+pub(crate) fn transform_async<F, A, Rs, Fut>(
+    f: F,
+    spawner: Arc<dyn Spawner>,
+) -> Box<impl functions::FnBoxClone>
+where
+    F: Fn(A) -> Fut + Send + Clone + 'static,
+    A: FromValues,
+    Rs: Replies,
+    Rs::Output: 'static,
+    Fut: Future<Output = Rs::Output> + Send + 'static,
+{
+    Box::new(
+        move |messages: Vec<Message>, panic_handler: Option<PanicHandler>| {
+            let (values, return_senders) = split_messages(messages);
+
+            let future = match panic::catch_unwind(AssertUnwindSafe(|| f(A::from_values(values)))) {
+                Ok(future) => future,
+                Err(payload) => {
+                    return report_panic::<Rs>(payload, return_senders, panic_handler);
+                }
+            };
+
+            spawner.spawn(Box::pin(async move {
+                match CatchUnwind::new(future).await {
+                    Ok(output) => Rs::send_replies(output, return_senders),
+                    Err(payload) => report_panic::<Rs>(payload, return_senders, panic_handler),
+                }
+            }));
+        },
+    )
+}
 
-    /// Transform function of `BidirJoinPattern` to use `Message` arguments.
-    pub(crate) fn transform_bidir<F, T, R>(f: F) -> Box<impl functions::unary::FnBoxClone>
-    where
-        F: Fn(T) -> R + Send + Clone + 'static,
-        T: Any + Send + 'static,
-        R: Any + Send + 'static,
-    {
-        Box::new(move |arg_and_sender: Message| {
-            let (arg, return_sender) = *arg_and_sender.downcast::<(T, Sender<R>)>().unwrap();
-
-            return_sender.send(f(arg)).unwrap();
-        })
+/// Split `Message`s into their values and return `Sender`s, keeping the order.
+fn split_messages(messages: Vec<Message>) -> (Vec<Payload>, Vec<Payload>) {
+    let mut values = Vec::with_capacity(messages.len());
+    let mut return_senders = Vec::new();
+
+    for msg in messages {
+        let (value, return_sender) = msg.into_parts();
+
+        values.extend(value);
+        return_senders.extend(return_sender);
     }
+
+    (values, return_senders)
 }
 
-/// Function transformers for functions stored with binary Join Patterns.
-pub(crate) mod binary {
-    use super::*;
-
-    /// Transform function of `SendJoinPattern` to use `Message` arguments.
-    pub(crate) fn transform_send<F, T, U>(f: F) -> Box<impl functions::binary::FnBoxClone>
-    where
-        F: Fn(T, U) -> () + Send + Clone + 'static,
-        T: Any + Send + 'static,
-        U: Any + Send + 'static,
-    {
-        Box::new(move |arg_1: Message, arg_2: Message| {
-            f(
-                *arg_1.downcast::<T>().unwrap(),
-                *arg_2.downcast::<U>().unwrap(),
-            );
-        })
+/// Report the panic of the function of a fired Join Pattern.
+///
+/// Each caller waiting on a reply receives `JunctionError::BodyPanicked`
+/// with the panic message. If no caller is waiting, the payload of the panic
+/// is handed to the given `PanicHandler` instead, if any.
+fn report_panic<Rs>(
+    payload: Payload,
+    return_senders: Vec<Payload>,
+    panic_handler: Option<PanicHandler>,
+) where
+    Rs: Replies,
+{
+    if return_senders.is_empty() {
+        if let Some(panic_handler) = panic_handler {
+            panic_handler(payload);
+        }
+    } else {
+        Rs::fail_replies(
+            JunctionError::BodyPanicked(panic_message(&payload)),
+            return_senders,
+        );
     }
+}
 
-    /// Transform function of `RecvJoinPattern` to use `Message` arguments.
-    pub(crate) fn transform_recv<F, T, R>(f: F) -> Box<impl functions::binary::FnBoxClone>
-    where
-        F: Fn(T) -> R + Send + Clone + 'static,
-        T: Any + Send + 'static,
-        R: Any + Send + 'static,
-    {
-        Box::new(move |arg: Message, return_sender: Message| {
-            let return_sender = *return_sender.downcast::<Sender<R>>().unwrap();
-            let arg = *arg.downcast::<T>().unwrap();
-
-            return_sender.send(f(arg)).unwrap();
-        })
+/// Return the message of a panic with the given payload.
+///
+/// Panics raised with a message carry it as a `&str` or a `String`. Any
+/// other payload is described the same way as by the standard panic hook.
+fn panic_message(payload: &Payload) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        String::from(*message)
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        String::from("Box<dyn Any>")
     }
+}
 
-    /// Transform function of `BidirJoinPattern` to use `Message` arguments.
-    pub(crate) fn transform_bidir<F, T, U, R>(f: F) -> Box<impl functions::binary::FnBoxClone>
-    where
-        F: Fn(T, U) -> R + Send + Clone + 'static,
-        T: Any + Send + 'static,
-        U: Any + Send + 'static,
-        R: Any + Send + 'static,
-    {
-        Box::new(move |arg_1: Message, arg_2_and_sender: Message| {
-            let arg_1 = *arg_1.downcast::<T>().unwrap();
-            let (arg_2, return_sender) = *arg_2_and_sender.downcast::<(U, Sender<R>)>().unwrap();
-
-            return_sender.send(f(arg_1, arg_2)).unwrap();
-        })
-    }
+/// `Future` resolving to the output of the wrapped `Future`, or to the
+/// payload of a panic raised while polling it.
+struct CatchUnwind<Fut> {
+    future: Pin<Box<Fut>>,
 }
 
-/// Function transformers for functions stored with ternary `JoinPattern`s.
-pub(crate) mod ternary {
-    use super::*;
-
-    /// Transform function of `SendJoinPattern` to use `Message` arguments.
-    pub(crate) fn transform_send<F, T, U, V>(f: F) -> Box<impl functions::ternary::FnBoxClone>
-    where
-        F: Fn(T, U, V) -> () + Send + Clone + 'static,
-        T: Any + Send + 'static,
-        U: Any + Send + 'static,
-        V: Any + Send + 'static,
-    {
-        Box::new(move |arg_1: Message, arg_2: Message, arg_3: Message| {
-            f(
-                *arg_1.downcast::<T>().unwrap(),
-                *arg_2.downcast::<U>().unwrap(),
-                *arg_3.downcast::<V>().unwrap(),
-            );
-        })
+impl<Fut> CatchUnwind<Fut> {
+    fn new(future: Fut) -> CatchUnwind<Fut> {
+        CatchUnwind {
+            future: Box::pin(future),
+        }
     }
+}
 
-    /// Transform function of `RecvJoinPattern` to use `Message` arguments.
-    pub(crate) fn transform_recv<F, T, U, R>(f: F) -> Box<impl functions::ternary::FnBoxClone>
-    where
-        F: Fn(T, U) -> R + Send + Clone + 'static,
-        T: Any + Send + 'static,
-        U: Any + Send + 'static,
-        R: Any + Send + 'static,
-    {
-        Box::new(
-            move |arg_1: Message, arg_2: Message, return_sender: Message| {
-                let return_sender = *return_sender.downcast::<Sender<R>>().unwrap();
-                let arg_1 = *arg_1.downcast::<T>().unwrap();
-                let arg_2 = *arg_2.downcast::<U>().unwrap();
-
-                return_sender.send(f(arg_1, arg_2)).unwrap();
-            },
-        )
-    }
+impl<Fut> Future for CatchUnwind<Fut>
+where
+    Fut: Future,
+{
+    type Output = Result<Fut::Output, Payload>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let future = self.future.as_mut();
 
-    /// Transform function of `BidirJoinPattern` to use `Message` arguments.
-    pub(crate) fn transform_bidir<F, T, U, V, R>(f: F) -> Box<impl functions::ternary::FnBoxClone>
-    where
-        F: Fn(T, U, V) -> R + Send + Clone + 'static,
-        T: Any + Send + 'static,
-        U: Any + Send + 'static,
-        V: Any + Send + 'static,
-        R: Any + Send + 'static,
-    {
-        Box::new(
-            move |arg_1: Message, arg_2: Message, arg_3_and_sender: Message| {
-                let arg_1 = *arg_1.downcast::<T>().unwrap();
-                let arg_2 = *arg_2.downcast::<U>().unwrap();
-                let (arg_3, return_sender) =
-                    *arg_3_and_sender.downcast::<(V, Sender<R>)>().unwrap();
-
-                return_sender.send(f(arg_1, arg_2, arg_3)).unwrap();
-            },
-        )
+        match panic::catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
     }
 }
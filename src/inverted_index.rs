@@ -46,6 +46,7 @@ where
     /// the values, in order. Otherwise, the given values are added in
     /// order at the end of the list of values already associated with the
     /// given key.
+    #[cfg(test)]
     pub(crate) fn insert_multiple(&mut self, key: K, values: impl IntoIterator<Item = V>) {
         match self.look_up_table.get_mut(&key) {
             Some(stored_values) => {
@@ -57,11 +58,34 @@ where
         }
     }
 
+    /// Remove all occurrences of the given value for the given key.
+    ///
+    /// Should no values remain for the given key afterwards, the key is
+    /// removed from the collection as well.
+    pub(crate) fn remove_single(&mut self, key: &K, value: &V)
+    where
+        V: PartialEq,
+    {
+        if let Some(values) = self.look_up_table.get_mut(key) {
+            let remaining: LinkedList<V> = std::mem::take(values)
+                .into_iter()
+                .filter(|v| v != value)
+                .collect();
+
+            if remaining.is_empty() {
+                self.look_up_table.remove(key);
+            } else {
+                *values = remaining;
+            }
+        }
+    }
+
     /// Retrieve an immutable reference to the first value for the given key.
     ///
     /// Retrieve an immutable reference to the first value added for the
     /// given key, if the key is available in the collection. Otherwise,
     /// return `None`.
+    #[cfg(test)]
     pub(crate) fn peek_first(&self, key: &K) -> Option<&V> {
         self.look_up_table.get(key)?.front()
     }
@@ -189,4 +213,45 @@ mod tests {
         // Then:
         assert_matching!([65], *actual.unwrap());
     }
+
+    #[test]
+    fn test_remove_single_keeps_other_values() {
+        // Given:
+        let mut index: InvertedIndex<char, i32> = InvertedIndex::new();
+
+        // When:
+        index.insert_multiple('A', vec![65, 66, 67]);
+        index.remove_single(&'A', &66);
+        let actual = index.peek_all(&'A');
+
+        // Then:
+        assert_matching!([65, 67], *actual.unwrap());
+    }
+
+    #[test]
+    fn test_remove_single_last_value_removes_key() {
+        // Given:
+        let mut index: InvertedIndex<char, i32> = InvertedIndex::new();
+
+        // When:
+        index.insert_single('A', 65);
+        index.remove_single(&'A', &65);
+
+        // Then:
+        assert!(index.peek_all(&'A').is_none());
+    }
+
+    #[test]
+    fn test_remove_single_unknown_key() {
+        // Given:
+        let mut index: InvertedIndex<char, i32> = InvertedIndex::new();
+
+        // When:
+        index.insert_single('A', 65);
+        index.remove_single(&'B', &65);
+        let actual = index.peek_all(&'A');
+
+        // Then:
+        assert_matching!([65], *actual.unwrap());
+    }
 }
@@ -4,12 +4,16 @@
 
 use std::any::Any;
 use std::ops::Drop;
-use std::sync::mpsc::{channel, RecvError, Sender};
+use std::sync::mpsc::channel;
+#[cfg(feature = "tokio")]
+use std::sync::Arc;
 
+use super::builder::JunctionBuilder;
 use super::channels::{BidirChannel, RecvChannel, SendChannel};
 use super::controller::Controller;
-use super::patterns::unary::{BidirPartialPattern, RecvPartialPattern, SendPartialPattern};
-use super::types::{ids, ControllerHandle, Packet};
+use super::errors::JunctionError;
+use super::patterns::PartialPattern;
+use super::types::{ids, ControllerHandle, Packet, PacketSender};
 
 /// Struct managing the creation of new channels and Join Patterns.
 ///
@@ -22,7 +26,10 @@ use super::types::{ids, ControllerHandle, Packet};
 pub struct Junction {
     id: ids::JunctionId,
     controller_handle: Option<ControllerHandle>,
-    sender: Sender<Packet>,
+    sender: PacketSender,
+    /// Capacity of the `SendChannel`s created by `send_channel`, which are
+    /// unbounded if `None`.
+    send_channel_capacity: Option<usize>,
 }
 
 #[allow(clippy::new_without_default)]
@@ -32,15 +39,80 @@ impl Junction {
     /// Create a new `Junction` and spawn a control thread in the background
     /// that will handle all incoming `Packet`s for this `Junction`. A
     /// `JoinHandle` to this control thread is stored alongside the `Junction`.
+    ///
+    /// Whenever multiple Join Patterns could fire at the same time, the one
+    /// that has not been fired for the longest time is chosen, as described
+    /// by `policies::LeastRecentlyFired`. The functions of fired Join Patterns
+    /// are run on a pool of up to 16 worker threads owned by the `Junction`.
+    ///
+    /// Note that a function blocked on a synchronous channel keeps its
+    /// worker busy. Once all 16 workers are blocked waiting for Join Patterns
+    /// of the same `Junction` to fire, these can never run, so the `Junction`
+    /// deadlocks. Use `JunctionBuilder::pool_size` for a larger pool or
+    /// `JunctionBuilder::executor` with `executors::ThreadPerJob` to spawn a
+    /// thread per firing instead.
     pub fn new() -> Junction {
+        Junction::builder().build()
+    }
+
+    /// Return a `JunctionBuilder` to configure a new `Junction`.
+    pub fn builder() -> JunctionBuilder {
+        JunctionBuilder::new()
+    }
+
+    /// Create a new `Junction` whose control thread runs as an async task on
+    /// the current tokio runtime.
+    ///
+    /// Works like `Junction::new`, except that instead of spawning a control
+    /// thread, the `Controller` of the `Junction` runs as a task on the tokio
+    /// runtime this function is called from. Its `ControllerHandle` can be
+    /// stopped with `ControllerHandle::stop_async` without blocking.
+    ///
+    /// Creating channels and adding Join Patterns never wait for the task,
+    /// so any tokio runtime will do, including a current-thread one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    #[cfg(feature = "tokio")]
+    pub fn new_tokio() -> Junction {
+        Junction::builder().build_tokio()
+    }
+
+    /// Create a new `Junction` and start the given `Controller` for it in a
+    /// control thread named `thread_name`, if any.
+    pub(crate) fn start(
+        controller: Controller,
+        thread_name: Option<String>,
+        send_channel_capacity: Option<usize>,
+    ) -> Junction {
         let (sender, receiver) = channel::<Packet>();
+        let sender = PacketSender::new(sender);
+
+        Junction {
+            id: ids::JunctionId::new(),
+            controller_handle: Some(controller.start(sender.clone(), receiver, thread_name)),
+            sender,
+            send_channel_capacity,
+        }
+    }
 
-        let controller = Controller::new();
+    /// Create a new `Junction` and start the given `Controller` for it as an
+    /// async task on the current tokio runtime.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn start_task(
+        controller: Controller,
+        send_channel_capacity: Option<usize>,
+    ) -> Junction {
+        let (sender, receiver) = channel::<Packet>();
+        let notify = Arc::new(tokio::sync::Notify::new());
+        let sender = PacketSender::with_notify(sender, Arc::clone(&notify));
 
         Junction {
             id: ids::JunctionId::new(),
-            controller_handle: Some(controller.start(sender.clone(), receiver)),
+            controller_handle: Some(controller.start_task(sender.clone(), receiver, notify)),
             sender,
+            send_channel_capacity,
         }
     }
 
@@ -64,33 +136,51 @@ impl Junction {
     /// Create and return a new `SendChannel` on this `Junction`.
     ///
     /// The generic parameter `T` is used to determine the type of values
-    /// that can be sent on this channel.
+    /// that can be sent on this channel. The channel is bounded if the
+    /// `Junction` was built with `JunctionBuilder::send_channel_capacity`.
+    pub fn send_channel<T>(&self) -> SendChannel<T>
+    where
+        T: Any + Send,
+    {
+        let send_channel = SendChannel::new(ids::ChannelId::new(), self.id, self.sender.clone());
+
+        match self.send_channel_capacity {
+            Some(capacity) => send_channel.bounded(capacity),
+            None => send_channel,
+        }
+    }
+
+    /// Create and return a new `SendChannel` on this `Junction` that holds at
+    /// most `capacity` messages waiting for a Join Pattern to consume them.
+    ///
+    /// Once the channel is full, `send` blocks until a Join Pattern consumes
+    /// one of its messages and `try_send` fails with `JunctionError::Full`.
+    /// Use it to keep a fast producer from growing the `Junction` without
+    /// bound. Note that blocking in `send` also blocks async tasks.
     ///
     /// # Panics
     ///
-    /// Panics if it received an error while trying to receive a new
-    /// channel ID from the control thread.
-    pub fn send_channel<T>(&self) -> SendChannel<T>
+    /// Panics if `capacity` is zero.
+    pub fn bounded_send_channel<T>(&self, capacity: usize) -> SendChannel<T>
     where
         T: Any + Send,
     {
-        SendChannel::new(self.new_channel_id().unwrap(), self.id, self.sender.clone())
+        if capacity == 0 {
+            panic!("Bounded SendChannel needs a capacity of at least one message!");
+        }
+
+        SendChannel::new(ids::ChannelId::new(), self.id, self.sender.clone()).bounded(capacity)
     }
 
     /// Create and return a new `RecvChannel` on this `Junction`.
     ///
     /// The generic parameter `R` is used to determine the type of values
     /// that can be received on this channel.
-    ///
-    /// # Panics
-    ///
-    /// Panics if it received an error while trying to receive a new
-    /// channel ID from the control thread.
     pub fn recv_channel<R>(&self) -> RecvChannel<R>
     where
         R: Any + Send,
     {
-        RecvChannel::new(self.new_channel_id().unwrap(), self.id, self.sender.clone())
+        RecvChannel::new(ids::ChannelId::new(), self.id, self.sender.clone())
     }
 
     /// Create and return a new `BidirChannel` on this `Junction`.
@@ -98,35 +188,12 @@ impl Junction {
     /// The generic parameter `T` is used to determine the type of values
     /// that can be sent on this channel while `R` is used to determine
     /// the type of values that can be received on this channel.
-    ///
-    /// # Panics
-    ///
-    /// Panics if it received an error while trying to receive the new
-    /// channel IDs from the control thread.
     pub fn bidir_channel<T, R>(&self) -> BidirChannel<T, R>
     where
         T: Any + Send,
         R: Any + Send,
     {
-        BidirChannel::new(self.new_channel_id().unwrap(), self.id, self.sender.clone())
-    }
-
-    /// Request ID for a new channel from control thread.
-    ///
-    /// # Panics
-    ///
-    /// Panics if request for new channel id could not be sent to
-    /// control thread.
-    fn new_channel_id(&self) -> Result<ids::ChannelId, RecvError> {
-        let (id_sender, id_receiver) = channel::<ids::ChannelId>();
-
-        self.sender
-            .send(Packet::NewChannelIdRequest {
-                return_sender: id_sender,
-            })
-            .unwrap();
-
-        id_receiver.recv()
+        BidirChannel::new(ids::ChannelId::new(), self.id, self.sender.clone())
     }
 
     /// Create new partial Join Pattern starting with a `SendChannel`.
@@ -135,20 +202,33 @@ impl Junction {
     ///
     /// Panics if the supplied `SendChannel` does not carry the same
     /// `JunctionID` as this `Junction`, i.e. has not been created by and is
-    /// associated with this `Junction`.
-    pub fn when<T>(&self, send_channel: &SendChannel<T>) -> SendPartialPattern<T>
+    /// associated with this `Junction`. Use `try_when` to handle this case
+    /// instead.
+    pub fn when<T>(&self, send_channel: &SendChannel<T>) -> PartialPattern<(T,), ()>
     where
         T: Any + Send,
     {
-        if send_channel.junction_id() == self.id {
-            SendPartialPattern::new(self.id, send_channel.strip(), self.sender.clone())
-        } else {
+        self.try_when(send_channel).unwrap_or_else(|_| {
             panic!(
                 "SendChannel is not associated with Junction! Please use \
                  a SendChannel created using the same Junction calling \
                  this function!"
-            );
-        }
+            )
+        })
+    }
+
+    /// Create new partial Join Pattern starting with a `SendChannel`.
+    ///
+    /// Return `JunctionError::WrongJunction` if the supplied `SendChannel`
+    /// has not been created by and is associated with this `Junction`.
+    pub fn try_when<T>(
+        &self,
+        send_channel: &SendChannel<T>,
+    ) -> Result<PartialPattern<(T,), ()>, JunctionError>
+    where
+        T: Any + Send,
+    {
+        self.start_pattern(send_channel.id(), send_channel.junction_id())
     }
 
     /// Create new partial Join Pattern starting with a `RecvChannel`.
@@ -157,20 +237,33 @@ impl Junction {
     ///
     /// Panics if the supplied `RecvChannel` does not carry the same
     /// `JunctionID` as this `Junction`, i.e. has not been created by and is
-    /// associated with this `Junction`.
-    pub fn when_recv<R>(&self, recv_channel: &RecvChannel<R>) -> RecvPartialPattern<R>
+    /// associated with this `Junction`. Use `try_when_recv` to handle this
+    /// case instead.
+    pub fn when_recv<R>(&self, recv_channel: &RecvChannel<R>) -> PartialPattern<(), (R,)>
     where
         R: Any + Send,
     {
-        if recv_channel.junction_id() == self.id {
-            RecvPartialPattern::new(recv_channel.strip(), self.sender.clone())
-        } else {
+        self.try_when_recv(recv_channel).unwrap_or_else(|_| {
             panic!(
                 "RecvChannel is not associated with Junction! Please use \
                  a RecvChannel created using the same Junction calling \
                  this function!"
-            );
-        }
+            )
+        })
+    }
+
+    /// Create new partial Join Pattern starting with a `RecvChannel`.
+    ///
+    /// Return `JunctionError::WrongJunction` if the supplied `RecvChannel`
+    /// has not been created by and is associated with this `Junction`.
+    pub fn try_when_recv<R>(
+        &self,
+        recv_channel: &RecvChannel<R>,
+    ) -> Result<PartialPattern<(), (R,)>, JunctionError>
+    where
+        R: Any + Send,
+    {
+        self.start_pattern(recv_channel.id(), recv_channel.junction_id())
     }
 
     /// Create a new partial Join Pattern starting with a `BidirChannel`.
@@ -179,20 +272,52 @@ impl Junction {
     ///
     /// Panics if the supplied `BidirChannel` does not carry the same
     /// `JunctionID` as this `Junction`, i.e. has not been created by and is
-    /// associated with this `Junction`.
-    pub fn when_bidir<T, R>(&self, bidir_channel: &BidirChannel<T, R>) -> BidirPartialPattern<T, R>
+    /// associated with this `Junction`. Use `try_when_bidir` to handle this
+    /// case instead.
+    pub fn when_bidir<T, R>(&self, bidir_channel: &BidirChannel<T, R>) -> PartialPattern<(T,), (R,)>
     where
         T: Any + Send,
         R: Any + Send,
     {
-        if bidir_channel.junction_id() == self.id {
-            BidirPartialPattern::new(bidir_channel.strip(), self.sender.clone())
-        } else {
+        self.try_when_bidir(bidir_channel).unwrap_or_else(|_| {
             panic!(
                 "BidirChannel is not associated with Junction! Please use \
                  a BidirChannel created using the same Junction calling \
                  this function!"
-            );
+            )
+        })
+    }
+
+    /// Create a new partial Join Pattern starting with a `BidirChannel`.
+    ///
+    /// Return `JunctionError::WrongJunction` if the supplied `BidirChannel`
+    /// has not been created by and is associated with this `Junction`.
+    pub fn try_when_bidir<T, R>(
+        &self,
+        bidir_channel: &BidirChannel<T, R>,
+    ) -> Result<PartialPattern<(T,), (R,)>, JunctionError>
+    where
+        T: Any + Send,
+        R: Any + Send,
+    {
+        self.start_pattern(bidir_channel.id(), bidir_channel.junction_id())
+    }
+
+    /// Create a partial Join Pattern starting with the given channel, if it
+    /// is associated with this `Junction`.
+    fn start_pattern<A, Rs>(
+        &self,
+        channel_id: ids::ChannelId,
+        junction_id: ids::JunctionId,
+    ) -> Result<PartialPattern<A, Rs>, JunctionError> {
+        if junction_id == self.id {
+            Ok(PartialPattern::new(
+                self.id,
+                channel_id,
+                self.sender.clone(),
+            ))
+        } else {
+            Err(JunctionError::WrongJunction)
         }
     }
 }
@@ -202,10 +327,1604 @@ impl Drop for Junction {
     ///
     /// If there is a `ControllerHandle` still available, use it to stop the
     /// associated `Controller` and join the control thread. Otherwise, no
-    /// action is needed.
+    /// action is needed. See `ControllerHandle::stop_on_drop` for a
+    /// `Controller` running as an async task.
     fn drop(&mut self) {
-        if self.controller_handle.is_some() {
-            self.controller_handle.take().unwrap().stop();
+        if let Some(mut controller_handle) = self.controller_handle.take() {
+            controller_handle.stop_on_drop();
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executors::{BoxFuture, Inline, ThreadPerJob};
+    use crate::policies::{Candidate, DeclarationOrder, FiringPolicy, OldestMessage};
+
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::Receiver;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll, Wake, Waker};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_quaternary_send_pattern() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<i32>();
+        let b = j.send_channel::<i32>();
+        let c = j.send_channel::<i32>();
+        let d = j.send_channel::<i32>();
+        let (tx, rx) = channel::<i32>();
+
+        // When:
+        j.when(&a)
+            .and(&b)
+            .and(&c)
+            .and(&d)
+            .then_do(move |a, b, c, d| tx.send(a + b + c + d).unwrap());
+
+        a.send(1).unwrap();
+        b.send(2).unwrap();
+        c.send(3).unwrap();
+        d.send(4).unwrap();
+
+        // Then:
+        assert_eq!(10, rx.recv().unwrap());
+    }
+
+    #[test]
+    fn test_senary_recv_pattern() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<i32>();
+        let b = j.send_channel::<String>();
+        let c = j.send_channel::<i32>();
+        let d = j.send_channel::<u8>();
+        let e = j.send_channel::<i32>();
+        let get = j.recv_channel::<String>();
+
+        // When:
+        j.when(&a)
+            .and(&b)
+            .and(&c)
+            .and(&d)
+            .and(&e)
+            .and_recv(&get)
+            .then_do(|a, b, c, d, e| format!("{}{}{}{}{}", a, b, c, d, e));
+
+        a.send(1).unwrap();
+        b.send(String::from("-")).unwrap();
+        c.send(2).unwrap();
+        d.send(3).unwrap();
+        e.send(4).unwrap();
+
+        // Then:
+        assert_eq!("1-234", get.recv().unwrap());
+    }
+
+    #[test]
+    fn test_repeated_channel_bidir_pattern() {
+        // Given:
+        let j = Junction::new();
+        let val = j.send_channel::<i32>();
+        let add = j.bidir_channel::<i32, i32>();
+
+        // When:
+        j.when(&val)
+            .and(&val)
+            .and(&val)
+            .and_bidir(&add)
+            .then_do(|a, b, c, d| a + b + c + d);
+
+        val.send(1).unwrap();
+        val.send(2).unwrap();
+        val.send(3).unwrap();
+
+        // Then:
+        assert_eq!(10, add.send_recv(4).unwrap());
+    }
+
+    #[test]
+    fn test_exchanger_pattern() {
+        // Given:
+        let j = Junction::new();
+        let a = j.bidir_channel::<i32, String>();
+        let b = j.bidir_channel::<String, i32>();
+
+        // When:
+        j.when_bidir(&a).and_bidir(&b).then_do(|x, y| (y, x));
+
+        let b_clone = b.clone();
+        let handle = thread::spawn(move || b_clone.send_recv(String::from("y")).unwrap());
+
+        // Then:
+        assert_eq!("y", a.send_recv(42).unwrap());
+        assert_eq!(42, handle.join().unwrap());
+    }
+
+    #[test]
+    fn test_multiple_recv_pattern() {
+        // Given:
+        let j = Junction::new();
+        let val = j.send_channel::<i32>();
+        let first = j.recv_channel::<i32>();
+        let second = j.recv_channel::<bool>();
+
+        // When:
+        j.when(&val)
+            .and_recv(&first)
+            .and_recv(&second)
+            .then_do(|v| (v + 1, v % 2 == 0));
+
+        let second_clone = second.clone();
+        let handle = thread::spawn(move || second_clone.recv().unwrap());
+
+        val.send(2).unwrap();
+
+        // Then:
+        assert_eq!(3, first.recv().unwrap());
+        assert!(handle.join().unwrap());
+    }
+
+    #[test]
+    fn test_recv_first_pattern() {
+        // Given:
+        let j = Junction::new();
+        let get = j.recv_channel::<i32>();
+        let val = j.send_channel::<i32>();
+        let scale = j.send_channel::<i32>();
+
+        // When:
+        j.when_recv(&get)
+            .and(&val)
+            .and(&scale)
+            .then_do(|v, s| v * s);
+
+        val.send(6).unwrap();
+        scale.send(7).unwrap();
+
+        // Then:
+        assert_eq!(42, get.recv().unwrap());
+    }
+
+    #[test]
+    fn test_bidir_first_pattern() {
+        // Given:
+        let j = Junction::new();
+        let swap = j.bidir_channel::<i32, i32>();
+        let val = j.send_channel::<i32>();
+        let get = j.recv_channel::<i32>();
+
+        // When:
+        j.when_bidir(&swap)
+            .and(&val)
+            .and_recv(&get)
+            .then_do(|new, old| (old, new));
+
+        let get_clone = get.clone();
+        let handle = thread::spawn(move || get_clone.recv().unwrap());
+
+        val.send(1).unwrap();
+
+        // Then:
+        assert_eq!(1, swap.send_recv(2).unwrap());
+        assert_eq!(2, handle.join().unwrap());
+    }
+
+    #[test]
+    fn test_guarded_pattern() {
+        // Given:
+        let j = Junction::new();
+        let bid = j.send_channel::<u32>();
+        let ask = j.send_channel::<u32>();
+        let (tx, rx) = channel::<(u32, u32)>();
+
+        // When:
+        j.when(&bid)
+            .and(&ask)
+            .guard(|bid, ask| bid >= ask)
+            .then_do(move |bid, ask| tx.send((bid, ask)).unwrap());
+
+        bid.send(10).unwrap();
+        ask.send(20).unwrap();
+        ask.send(15).unwrap();
+        bid.send(16).unwrap();
+
+        // Then:
+        assert_eq!((16, 15), rx.recv().unwrap());
+
+        bid.send(25).unwrap();
+
+        assert_eq!((25, 20), rx.recv().unwrap());
+    }
+
+    #[test]
+    fn test_guarded_pattern_repeated_channel() {
+        // Given:
+        let j = Junction::new();
+        let val = j.send_channel::<i32>();
+        let get = j.recv_channel::<(i32, i32)>();
+
+        // When:
+        j.when(&val)
+            .and(&val)
+            .guard(|a, b| a > b)
+            .and_recv(&get)
+            .then_do(|a, b| (a, b));
+
+        val.send(1).unwrap();
+        val.send(2).unwrap();
+
+        // Then:
+        assert_eq!((2, 1), get.recv().unwrap());
+    }
+
+    #[test]
+    fn test_panicking_guard_is_not_satisfied() {
+        // Given:
+        let j = Junction::new();
+        let val = j.send_channel::<i32>();
+        let get = j.recv_channel::<i32>();
+
+        j.when(&val)
+            .guard(|x| {
+                if *x == 0 {
+                    panic!("guard panicked")
+                } else {
+                    true
+                }
+            })
+            .and_recv(&get)
+            .then_do(|x| x);
+
+        // When:
+        val.send(0).unwrap();
+        val.send(42).unwrap();
+
+        // Then:
+        assert_eq!(42, get.recv().unwrap());
+    }
+
+    #[test]
+    fn test_removed_pattern_does_not_fire() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<i32>();
+        let (tx, rx) = channel::<&str>();
+        let tx_clone = tx.clone();
+
+        // When:
+        let handle = j.when(&a).then_do(move |_| tx.send("removed").unwrap());
+        handle.remove().unwrap();
+
+        j.when(&a).then_do(move |_| tx_clone.send("kept").unwrap());
+
+        a.send(1).unwrap();
+        a.send(2).unwrap();
+
+        // Then:
+        assert_eq!("kept", rx.recv().unwrap());
+        assert_eq!("kept", rx.recv().unwrap());
+    }
+
+    #[test]
+    fn test_pattern_handles_have_distinct_ids() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<i32>();
+
+        // When:
+        let first = j.when(&a).then_do(|_| {});
+        let second = j.when(&a).and(&a).then_do(|_, _| {});
+
+        // Then:
+        assert_ne!(first.id(), second.id());
+    }
+
+    #[test]
+    fn test_once_pattern_fires_only_once() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<i32>();
+        let get = j.recv_channel::<i32>();
+        let (tx, rx) = channel::<i32>();
+
+        // When:
+        j.when(&a).then_do_once(move |v| tx.send(v).unwrap());
+
+        a.send(1).unwrap();
+        a.send(2).unwrap();
+
+        let fired_with = rx.recv().unwrap();
+
+        j.when(&a).and_recv(&get).then_do(|v| v);
+
+        // Then:
+        assert_eq!(1, fired_with);
+        assert_eq!(2, get.recv().unwrap());
+        assert!(rx.recv().is_err());
+    }
+
+    #[test]
+    fn test_once_pattern_with_reply() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<String>();
+        let b = j.bidir_channel::<String, String>();
+
+        // When:
+        j.when(&a)
+            .and_bidir(&b)
+            .then_do_once(|first, second| first + &second);
+
+        a.send(String::from("Hello, ")).unwrap();
+
+        // Then:
+        assert_eq!("Hello, World", b.send_recv(String::from("World")).unwrap());
+    }
+
+    #[test]
+    fn test_max_concurrency_holds_back_firings() {
+        // Given:
+        let j = Junction::builder().executor(ThreadPerJob).build();
+        let a = j.send_channel::<i32>();
+        let (started_tx, started_rx) = channel::<i32>();
+        let (release_tx, release_rx) = channel::<()>();
+        let release_rx = Arc::new(Mutex::new(release_rx));
+
+        j.when(&a).max_concurrency(2).then_do(move |v| {
+            started_tx.send(v).unwrap();
+            release_rx.lock().unwrap().recv().unwrap();
+        });
+
+        // When:
+        for i in 0..3 {
+            a.send(i).unwrap();
+        }
+
+        let mut first_started = vec![started_rx.recv().unwrap(), started_rx.recv().unwrap()];
+        first_started.sort_unstable();
+        let held_back = started_rx.recv_timeout(Duration::from_millis(50));
+
+        release_tx.send(()).unwrap();
+        let last_started = started_rx.recv().unwrap();
+        release_tx.send(()).unwrap();
+        release_tx.send(()).unwrap();
+
+        // Then:
+        assert_eq!(vec![0, 1], first_started);
+        assert!(held_back.is_err());
+        assert_eq!(2, last_started);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_max_concurrency() {
+        let j = Junction::new();
+        let a = j.send_channel::<()>();
+
+        j.when(&a).max_concurrency(0).then_do(|_| {});
+    }
+
+    #[test]
+    fn test_mut_pattern_keeps_state() {
+        // Given:
+        let j = Junction::new();
+        let add = j.bidir_channel::<i32, i32>();
+        let mut total = 0;
+
+        // When:
+        j.when_bidir(&add).then_do_mut(move |v| {
+            total += v;
+            total
+        });
+
+        // Then:
+        assert_eq!(1, add.send_recv(1).unwrap());
+        assert_eq!(3, add.send_recv(2).unwrap());
+        assert_eq!(6, add.send_recv(3).unwrap());
+    }
+
+    #[test]
+    fn test_mut_pattern_survives_panic() {
+        // Given:
+        let j = Junction::new();
+        let add = j.bidir_channel::<i32, i32>();
+        let mut total = 0;
+
+        j.when_bidir(&add).then_do_mut(move |v| {
+            total += v;
+
+            if v < 0 {
+                panic!("negative value");
+            }
+
+            total
+        });
+
+        // When:
+        let panicked = add.send_recv(-1);
+
+        // Then:
+        assert!(panicked.is_err());
+        assert_eq!(1, add.send_recv(2).unwrap());
+    }
+
+    #[test]
+    fn test_mut_pattern_invocations_never_overlap() {
+        // Given:
+        let j = Junction::builder().executor(ThreadPerJob).build();
+        let a = j.send_channel::<i32>();
+        let (tx, rx) = channel::<(bool, i32)>();
+        let running = Arc::new(AtomicBool::new(false));
+
+        j.when(&a).then_do_mut(move |v| {
+            let overlapped = running.swap(true, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(5));
+            running.store(false, Ordering::SeqCst);
+
+            tx.send((overlapped, v)).unwrap();
+        });
+
+        // When:
+        for i in 0..10 {
+            a.send(i).unwrap();
+        }
+
+        let fired: Vec<(bool, i32)> = (0..10).map(|_| rx.recv().unwrap()).collect();
+
+        // Then:
+        assert!(fired.iter().all(|&(overlapped, _)| !overlapped));
+        assert_eq!(
+            (0..10).collect::<Vec<i32>>(),
+            fired.iter().map(|&(_, v)| v).collect::<Vec<i32>>()
+        );
+    }
+
+    #[test]
+    fn test_higher_priority_pattern_fires_first() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<()>();
+        let b = j.send_channel::<()>();
+        let (tx, rx) = channel::<&str>();
+        let tx_clone = tx.clone();
+
+        j.when(&a).then_do(move |_| tx.send("low").unwrap());
+        j.when(&a)
+            .and(&b)
+            .priority(1)
+            .then_do(move |_, _| tx_clone.send("high").unwrap());
+
+        // When:
+        let mut fired = Vec::new();
+
+        for _ in 0..3 {
+            b.send(()).unwrap();
+            a.send(()).unwrap();
+            fired.push(rx.recv().unwrap());
+        }
+
+        // Then:
+        assert_eq!(vec!["high", "high", "high"], fired);
+    }
+
+    #[test]
+    fn test_equal_priority_falls_back_to_last_fired() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<()>();
+        let b = j.send_channel::<()>();
+        let (tx, rx) = channel::<&str>();
+        let tx_clone = tx.clone();
+
+        j.when(&a)
+            .and(&b)
+            .priority(1)
+            .then_do(move |_, _| tx.send("first").unwrap());
+        j.when(&a)
+            .and(&b)
+            .priority(1)
+            .then_do(move |_, _| tx_clone.send("second").unwrap());
+
+        // When:
+        let mut fired = Vec::new();
+
+        for _ in 0..4 {
+            a.send(()).unwrap();
+            b.send(()).unwrap();
+            fired.push(rx.recv().unwrap());
+        }
+
+        // Then:
+        assert_ne!(fired[0], fired[1]);
+        assert_eq!(fired[0], fired[2]);
+        assert_eq!(fired[1], fired[3]);
+    }
+
+    #[test]
+    fn test_declaration_order_policy() {
+        // Given:
+        let j = Junction::builder().firing_policy(DeclarationOrder).build();
+        let a = j.send_channel::<()>();
+        let (tx, rx) = channel::<&str>();
+        let tx_clone = tx.clone();
+
+        j.when(&a).then_do(move |_| tx.send("first").unwrap());
+        j.when(&a)
+            .then_do(move |_| tx_clone.send("second").unwrap());
+
+        // When:
+        let mut fired = Vec::new();
+
+        for _ in 0..3 {
+            a.send(()).unwrap();
+            fired.push(rx.recv().unwrap());
+        }
+
+        // Then:
+        assert_eq!(vec!["first", "first", "first"], fired);
+    }
+
+    #[test]
+    fn test_oldest_message_policy() {
+        // Given:
+        let j = Junction::builder().firing_policy(OldestMessage).build();
+        let a = j.send_channel::<()>();
+        let b = j.send_channel::<()>();
+        let c = j.send_channel::<()>();
+        let (tx, rx) = channel::<&str>();
+        let tx_clone = tx.clone();
+
+        j.when(&a)
+            .and(&c)
+            .then_do(move |_, _| tx.send("a").unwrap());
+        j.when(&b)
+            .and(&c)
+            .then_do(move |_, _| tx_clone.send("b").unwrap());
+
+        // When:
+        b.send(()).unwrap();
+        a.send(()).unwrap();
+        c.send(()).unwrap();
+
+        // Then:
+        assert_eq!("b", rx.recv().unwrap());
+    }
+
+    #[test]
+    fn test_pattern_fires_for_messages_sent_before_declaration() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<i32>();
+        let b = j.send_channel::<i32>();
+        let (tx, rx) = channel::<i32>();
+
+        for i in 0..3 {
+            a.send(i).unwrap();
+            b.send(10 * i).unwrap();
+        }
+
+        // When:
+        j.when(&a)
+            .and(&b)
+            .then_do(move |x, y| tx.send(x + y).unwrap());
+
+        // Then:
+        let mut sums: Vec<i32> = (0..3).map(|_| rx.recv().unwrap()).collect();
+        sums.sort_unstable();
+
+        assert_eq!(vec![0, 11, 22], sums);
+    }
+
+    /// Create a `Junction` running functions inline on the control thread,
+    /// with two Join Patterns on a channel `a`. Sending `1` fires the one
+    /// replying `"unlock"`, which opens the guard of the other, replying
+    /// `"open"` for any value from then on. The latter is declared first.
+    fn unlocking_junction() -> (Junction, SendChannel<i32>, Receiver<&'static str>) {
+        let j = Junction::builder().executor(Inline).build();
+        let a = j.send_channel::<i32>();
+        let (tx, rx) = channel::<&str>();
+        let tx_clone = tx.clone();
+        let open = Arc::new(AtomicBool::new(false));
+        let open_clone = Arc::clone(&open);
+
+        j.when(&a)
+            .guard(move |_| open.load(Ordering::SeqCst))
+            .then_do(move |_| tx.send("open").unwrap());
+        j.when(&a).guard(|x| *x == 1).then_do(move |_| {
+            open_clone.store(true, Ordering::SeqCst);
+            tx_clone.send("unlock").unwrap();
+        });
+
+        (j, a, rx)
+    }
+
+    #[test]
+    fn test_fires_until_quiescent() {
+        // Given:
+        let (_j, a, rx) = unlocking_junction();
+
+        a.send(2).unwrap();
+
+        // When:
+        a.send(1).unwrap();
+
+        // Then:
+        assert_eq!(Ok("unlock"), rx.recv_timeout(Duration::from_secs(1)));
+        assert_eq!(Ok("open"), rx.recv_timeout(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_fires_until_quiescent_across_channels() {
+        // Given:
+        let j = Junction::builder().executor(Inline).build();
+        let a = j.send_channel::<()>();
+        let get = j.recv_channel::<&'static str>();
+        let open = Arc::new(AtomicBool::new(false));
+        let open_clone = Arc::clone(&open);
+        let (tx, rx) = channel::<Result<&str, JunctionError>>();
+
+        j.when(&a)
+            .then_do(move |_| open.store(true, Ordering::SeqCst));
+        j.when_recv(&get)
+            .guard(move || open_clone.load(Ordering::SeqCst))
+            .then_do(|| "open");
+
+        let reply = get.recv_async();
+        thread::spawn(move || tx.send(block_on(reply)).unwrap());
+
+        // When:
+        a.send(()).unwrap();
+
+        // Then:
+        assert_eq!(Ok(Ok("open")), rx.recv_timeout(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_firings_in_one_packet_are_ordered() {
+        // Given:
+        let (_j, a, rx) = unlocking_junction();
+
+        a.send(2).unwrap();
+        a.send(1).unwrap();
+
+        // When:
+        a.send(1).unwrap();
+
+        // Then:
+        let fired: Vec<&str> = (0..3).map(|_| rx.recv().unwrap()).collect();
+
+        assert_eq!(vec!["unlock", "open", "unlock"], fired);
+    }
+
+    #[test]
+    fn test_bodies_run_on_bounded_pool() {
+        // Given:
+        let j = Junction::builder().pool_size(1).build();
+        let a = j.send_channel::<()>();
+        let (tx, rx) = channel::<thread::ThreadId>();
+
+        j.when(&a)
+            .then_do(move |_| tx.send(thread::current().id()).unwrap());
+
+        // When:
+        for _ in 0..10 {
+            a.send(()).unwrap();
+        }
+
+        // Then:
+        let ids: Vec<thread::ThreadId> = (0..10).map(|_| rx.recv().unwrap()).collect();
+
+        assert!(ids.iter().all(|&id| id == ids[0]));
+    }
+
+    #[test]
+    fn test_junction_executor() {
+        // Given:
+        let mut j = Junction::builder().executor(Inline).build();
+        let mut controller_handle = j.controller_handle().unwrap();
+        let a = j.send_channel::<()>();
+        let (tx, rx) = channel::<thread::ThreadId>();
+
+        j.when(&a)
+            .then_do(move |_| tx.send(thread::current().id()).unwrap());
+
+        // When:
+        a.send(()).unwrap();
+
+        // Then:
+        assert_eq!(controller_handle.thread().unwrap().id(), rx.recv().unwrap());
+
+        controller_handle.stop();
+    }
+
+    #[test]
+    fn test_add_pattern_from_inline_body() {
+        // Given:
+        let j = Junction::builder().executor(Inline).build();
+        let a = j.send_channel::<()>();
+        let b = j.send_channel::<i32>();
+        let (tx, rx) = channel::<i32>();
+        let pending = j.when(&b);
+
+        j.when(&a).then_do_once(move |_| {
+            pending.then_do(move |x| tx.send(x).unwrap());
+        });
+
+        // When:
+        a.send(()).unwrap();
+        b.send(42).unwrap();
+
+        // Then:
+        assert_eq!(Ok(42), rx.recv_timeout(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_pattern_executor_overrides_junction_executor() {
+        // Given:
+        let mut j = Junction::builder().executor(Inline).build();
+        let mut controller_handle = j.controller_handle().unwrap();
+        let a = j.send_channel::<()>();
+        let (tx, rx) = channel::<thread::ThreadId>();
+
+        j.when(&a)
+            .executor(ThreadPerJob)
+            .then_do(move |_| tx.send(thread::current().id()).unwrap());
+
+        // When:
+        a.send(()).unwrap();
+
+        // Then:
+        assert_ne!(controller_handle.thread().unwrap().id(), rx.recv().unwrap());
+
+        controller_handle.stop();
+    }
+
+    #[test]
+    fn test_recv_timeout_replies_in_time() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<i32>();
+        let get = j.recv_channel::<i32>();
+
+        j.when(&a).and_recv(&get).then_do(|v| v);
+
+        // When:
+        a.send(42).unwrap();
+
+        // Then:
+        assert_eq!(Ok(42), get.recv_timeout(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_recv_timeout_withdraws_request() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<i32>();
+        let get = j.recv_channel::<i32>();
+
+        j.when(&a).and_recv(&get).then_do(|v| v);
+
+        // When:
+        let timed_out = get.recv_timeout(Duration::from_millis(10));
+        a.send(42).unwrap();
+
+        // Then:
+        assert_eq!(Err(JunctionError::Timeout), timed_out);
+        assert_eq!(42, get.recv().unwrap());
+    }
+
+    #[test]
+    fn test_send_recv_timeout_withdraws_message() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<i32>();
+        let add = j.bidir_channel::<i32, i32>();
+
+        j.when(&a).and_bidir(&add).then_do(|x, y| x + y);
+
+        // When:
+        let timed_out = add.send_recv_timeout(1, Duration::from_millis(10));
+        a.send(40).unwrap();
+
+        // Then:
+        assert_eq!(Err(JunctionError::Timeout), timed_out);
+        assert_eq!(42, add.send_recv(2).unwrap());
+    }
+
+    #[test]
+    fn test_try_recv_fires_right_away() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<i32>();
+        let get = j.recv_channel::<i32>();
+
+        j.when(&a).and_recv(&get).then_do(|v| v);
+
+        // When:
+        a.send(42).unwrap();
+
+        // Then:
+        assert_eq!(Ok(42), get.try_recv());
+    }
+
+    #[test]
+    fn test_try_recv_would_block_leaves_nothing_behind() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<i32>();
+        let get = j.recv_channel::<i32>();
+
+        j.when(&a).and_recv(&get).then_do(|v| v);
+
+        // When:
+        let would_block = get.try_recv();
+        a.send(42).unwrap();
+
+        // Then:
+        assert_eq!(Err(JunctionError::WouldBlock), would_block);
+        assert_eq!(42, get.recv().unwrap());
+    }
+
+    #[test]
+    fn test_try_send_recv_would_block_leaves_nothing_behind() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<i32>();
+        let add = j.bidir_channel::<i32, i32>();
+
+        j.when(&a).and_bidir(&add).then_do(|x, y| x + y);
+
+        // When:
+        let would_block = add.try_send_recv(1);
+        a.send(40).unwrap();
+
+        // Then:
+        assert_eq!(Err(JunctionError::WouldBlock), would_block);
+        assert_eq!(Ok(42), add.try_send_recv(2));
+    }
+
+    #[test]
+    fn test_try_send_recv_rejected_by_guard_leaves_nothing_behind() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<i32>();
+        let add = j.bidir_channel::<i32, i32>();
+
+        j.when(&a)
+            .and_bidir(&add)
+            .guard(|x, y| x < y)
+            .then_do(|x, y| x + y);
+        a.send(1).unwrap();
+
+        // When:
+        let would_block = add.try_send_recv(0);
+        a.send(40).unwrap();
+
+        // Then:
+        assert_eq!(Err(JunctionError::WouldBlock), would_block);
+        assert_eq!(Ok(3), add.try_send_recv(2));
+        assert_eq!(Ok(82), add.try_send_recv(42));
+    }
+
+    #[test]
+    fn test_abandoned_recv_is_discarded() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<i32>();
+        let get = j.recv_channel::<i32>();
+
+        j.when(&a).and_recv(&get).then_do(|v| v);
+
+        // When:
+        drop(get.recv_async());
+        a.send(42).unwrap();
+
+        // Then:
+        assert_eq!(42, get.recv().unwrap());
+    }
+
+    #[test]
+    fn test_abandoned_recvs_are_discarded_until_one_is_waited_on() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<i32>();
+        let get = j.recv_channel::<i32>();
+
+        j.when(&a).and_recv(&get).then_do(|v| v);
+
+        // When:
+        drop(get.recv_async());
+        drop(get.recv_async());
+        let reply = get.recv_async();
+        a.send(42).unwrap();
+        a.send(43).unwrap();
+
+        // Then:
+        assert_eq!(Ok(42), block_on(reply));
+        assert_eq!(43, get.recv().unwrap());
+    }
+
+    #[test]
+    fn test_abandoned_send_recv_is_discarded() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<i32>();
+        let add = j.bidir_channel::<i32, i32>();
+
+        j.when(&a).and_bidir(&add).then_do(|x, y| x + y);
+
+        // When:
+        drop(add.send_recv_async(1));
+        a.send(40).unwrap();
+
+        // Then:
+        assert_eq!(42, add.send_recv(2).unwrap());
+    }
+
+    #[test]
+    fn test_run_on_caller_runs_on_waiting_thread() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<i32>();
+        let get = j.bidir_channel::<i32, (i32, thread::ThreadId)>();
+
+        j.when(&a)
+            .and_bidir(&get)
+            .run_on_caller()
+            .then_do(|x, y| (x + y, thread::current().id()));
+
+        // When:
+        a.send(40).unwrap();
+        let reply = get.send_recv(2).unwrap();
+
+        // Then:
+        assert_eq!((42, thread::current().id()), reply);
+    }
+
+    #[test]
+    fn test_run_on_caller_async_uses_executor() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<i32>();
+        let get = j.recv_channel::<thread::ThreadId>();
+
+        j.when(&a)
+            .and_recv(&get)
+            .run_on_caller()
+            .then_do(|_| thread::current().id());
+
+        // When:
+        let reply = get.recv_async();
+        a.send(42).unwrap();
+
+        // Then:
+        assert_ne!(thread::current().id(), block_on(reply).unwrap());
+    }
+
+    #[test]
+    fn test_send_on_stopped_junction_returns_value() {
+        // Given:
+        let mut j = Junction::new();
+        let a = j.send_channel::<String>();
+
+        // When:
+        j.controller_handle().unwrap().stop();
+        let error = a.send(String::from("unsent")).unwrap_err();
+
+        // Then:
+        assert_eq!(JunctionError::ControllerStopped, error.error());
+        assert_eq!("unsent", error.into_inner());
+    }
+
+    #[test]
+    fn test_patterns_on_stopped_junction() {
+        // Given:
+        let mut j = Junction::new();
+        let a = j.send_channel::<()>();
+        let handle = j.when(&a).then_do(|_| {});
+
+        // When:
+        j.controller_handle().unwrap().stop();
+        let added = j.when(&a).then_do(|_| {});
+
+        // Then:
+        assert_eq!(Err(JunctionError::ControllerStopped), handle.remove());
+        assert_eq!(Err(JunctionError::ControllerStopped), added.remove());
+    }
+
+    #[test]
+    fn test_recv_body_panicked() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<()>();
+        let get = j.recv_channel::<i32>();
+
+        j.when(&a)
+            .and_recv(&get)
+            .then_do(|_| -> i32 { panic!("body panicked") });
+
+        // When:
+        a.send(()).unwrap();
+
+        // Then:
+        assert_eq!(
+            Err(JunctionError::BodyPanicked(String::from("body panicked"))),
+            get.recv()
+        );
+    }
+
+    #[test]
+    fn test_body_panic_keeps_worker_alive() {
+        // Given:
+        let j = Junction::builder().pool_size(1).build();
+        let a = j.send_channel::<()>();
+        let get = j.bidir_channel::<i32, i32>();
+
+        j.when(&a).then_do(|_| panic!("body panicked"));
+        j.when_bidir(&get).then_do(|x| x);
+
+        // When:
+        a.send(()).unwrap();
+
+        // Then:
+        assert_eq!(42, get.send_recv(42).unwrap());
+    }
+
+    #[test]
+    fn test_run_on_caller_body_panicked() {
+        // Given:
+        let j = Junction::new();
+        let get = j.recv_channel::<i32>();
+
+        j.when_recv(&get)
+            .run_on_caller()
+            .then_do(|| -> i32 { panic!("body panicked") });
+
+        // Then:
+        assert_eq!(
+            Err(JunctionError::BodyPanicked(String::from("body panicked"))),
+            get.recv()
+        );
+    }
+
+    #[test]
+    fn test_inline_body_panic_keeps_controller_alive() {
+        // Given:
+        let j = Junction::builder().executor(Inline).build();
+        let get = j.recv_channel::<i32>();
+        let add = j.bidir_channel::<i32, i32>();
+
+        j.when_recv(&get)
+            .then_do(|| -> i32 { panic!("body panicked") });
+        j.when_bidir(&add).then_do(|x| x + 1);
+
+        // Then:
+        assert_eq!(
+            Err(JunctionError::BodyPanicked(String::from("body panicked"))),
+            get.recv()
+        );
+        assert_eq!(42, add.send_recv(41).unwrap());
+    }
+
+    /// `FiringPolicy` selecting a position outside of the candidates.
+    struct OutOfRange;
+
+    impl FiringPolicy for OutOfRange {
+        fn select(&mut self, candidates: &[Candidate]) -> usize {
+            candidates.len()
+        }
+    }
+
+    #[test]
+    fn test_controller_panic_stops_callers() {
+        // Given:
+        let j = Junction::builder().firing_policy(OutOfRange).build();
+        let get = j.recv_channel::<i32>();
+
+        j.when_recv(&get).then_do(|| 42);
+
+        // When:
+        let reply = get.recv();
+
+        // Then:
+        assert_eq!(Err(JunctionError::ControllerStopped), reply);
+
+        // Stopping the `Controller` fails once it has panicked.
+        std::mem::forget(j);
+    }
+
+    #[test]
+    fn test_bounded_try_send_full() {
+        // Given:
+        let j = Junction::new();
+        let a = j.bounded_send_channel::<i32>(1);
+        let b = j.send_channel::<()>();
+
+        j.when(&a).and(&b).then_do(|_, _| {});
+
+        // When:
+        a.try_send(1).unwrap();
+        let error = a.try_send(2).unwrap_err();
+
+        // Then:
+        assert_eq!(JunctionError::Full, error.error());
+        assert_eq!(2, error.into_inner());
+    }
+
+    #[test]
+    fn test_bounded_send_blocks_until_consumed() {
+        // Given:
+        let j = Junction::new();
+        let a = j.bounded_send_channel::<i32>(1);
+        let b = j.send_channel::<()>();
+        let (tx, rx) = channel::<i32>();
+        let (sent_tx, sent_rx) = channel::<()>();
+        let a_clone = a.clone();
+
+        j.when(&a).and(&b).then_do(move |x, _| tx.send(x).unwrap());
+
+        a.send(1).unwrap();
+
+        thread::spawn(move || {
+            a_clone.send(2).unwrap();
+            sent_tx.send(()).unwrap();
+        });
+
+        // When:
+        let blocked = sent_rx.recv_timeout(Duration::from_millis(50));
+        b.send(()).unwrap();
+
+        // Then:
+        assert!(blocked.is_err());
+        assert_eq!(1, rx.recv().unwrap());
+        assert!(sent_rx.recv().is_ok());
+
+        b.send(()).unwrap();
+        assert_eq!(2, rx.recv().unwrap());
+    }
+
+    #[test]
+    fn test_body_sends_on_full_bounded_channel() {
+        // Given:
+        let j = Junction::builder().pool_size(1).build();
+        let a = j.send_channel::<()>();
+        let b = j.bounded_send_channel::<i32>(1);
+        let (tx, rx) = channel::<i32>();
+        let b_clone = b.clone();
+
+        j.when(&a).then_do(move |_| {
+            b_clone.send(1).unwrap();
+            b_clone.send(2).unwrap();
+        });
+        j.when(&b).then_do(move |x| tx.send(x).unwrap());
+
+        // When:
+        a.send(()).unwrap();
+
+        // Then:
+        assert_eq!(Ok(1), rx.recv_timeout(Duration::from_secs(10)));
+        assert_eq!(Ok(2), rx.recv_timeout(Duration::from_secs(10)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_bounded_zero_capacity() {
+        let j = Junction::new();
+
+        j.bounded_send_channel::<()>(0);
+    }
+
+    #[test]
+    fn test_try_when_wrong_junction() {
+        // Given:
+        let j = Junction::new();
+        let other = Junction::new();
+        let a = j.send_channel::<()>();
+        let get = other.recv_channel::<()>();
+
+        // Then:
+        assert!(matches!(
+            other.try_when(&a),
+            Err(JunctionError::WrongJunction)
+        ));
+        assert!(matches!(
+            j.try_when(&a).unwrap().try_and_recv(&get),
+            Err(JunctionError::WrongJunction)
+        ));
+    }
+
+    /// Run the given `Future` to completion on the current thread.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        struct ThreadWaker(thread::Thread);
+
+        impl Wake for ThreadWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    /// `Waker` that does nothing when woken.
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    #[test]
+    fn test_recv_async() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<i32>();
+        let get = j.recv_channel::<i32>();
+
+        j.when(&a).and_recv(&get).then_do(|v| v);
+
+        // When:
+        let reply = get.recv_async();
+        a.send(42).unwrap();
+
+        // Then:
+        assert_eq!(42, block_on(reply).unwrap());
+    }
+
+    #[test]
+    fn test_send_recv_async() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<i32>();
+        let b = j.bidir_channel::<i32, i32>();
+
+        j.when(&a).and_bidir(&b).then_do(|x, y| x + y);
+
+        // When:
+        a.send(1).unwrap();
+        let reply = b.send_recv_async(2);
+
+        // Then:
+        assert_eq!(3, block_on(reply).unwrap());
+    }
+
+    #[test]
+    fn test_recv_async_is_pending_until_fired() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<i32>();
+        let get = j.recv_channel::<i32>();
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut cx = Context::from_waker(&waker);
+
+        j.when(&a).and_recv(&get).then_do(|v| v);
+
+        // When:
+        let mut reply = get.recv_async();
+        let first_poll = Pin::new(&mut reply).poll(&mut cx);
+
+        a.send(42).unwrap();
+
+        // Then:
+        assert!(first_poll.is_pending());
+        assert_eq!(42, block_on(reply).unwrap());
+    }
+
+    #[test]
+    fn test_recv_async_stopped_junction() {
+        // Given:
+        let j = Junction::new();
+        let get = j.recv_channel::<i32>();
+        let a = j.send_channel::<i32>();
+
+        j.when(&a).and_recv(&get).then_do(|v| v);
+
+        // When:
+        let reply = get.recv_async();
+        drop(j);
+
+        // Then:
+        assert_eq!(Err(JunctionError::ControllerStopped), block_on(reply));
+    }
+
+    /// `Spawner` running each `Future` to completion on a new thread.
+    fn thread_spawner(future: BoxFuture) {
+        thread::spawn(move || block_on(future));
+    }
+
+    #[test]
+    fn test_async_body_replies_with_output() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<i32>();
+        let b = j.bidir_channel::<i32, i32>();
+
+        j.when(&a)
+            .and_bidir(&b)
+            .then_do_async(thread_spawner, |x, y| async move { x * y });
+
+        // When:
+        a.send(6).unwrap();
+
+        // Then:
+        assert_eq!(42, b.send_recv(7).unwrap());
+    }
+
+    #[test]
+    fn test_async_body_awaits_other_junction() {
+        // Given:
+        let other = Junction::new();
+        let value = other.send_channel::<i32>();
+        let get_value = other.recv_channel::<i32>();
+
+        other.when(&value).and_recv(&get_value).then_do(|v| v);
+
+        let j = Junction::new();
+        let get = j.recv_channel::<i32>();
+
+        j.when_recv(&get).then_do_async(thread_spawner, move || {
+            let reply = get_value.recv_async();
+
+            async move { reply.await.unwrap() + 1 }
+        });
+
+        // When:
+        let result = get.recv_async();
+        value.send(41).unwrap();
+
+        // Then:
+        assert_eq!(42, block_on(result).unwrap());
+    }
+
+    #[test]
+    fn test_async_body_panicked() {
+        // Given:
+        let j = Junction::new();
+        let get = j.recv_channel::<i32>();
+
+        j.when_recv(&get).then_do_async(thread_spawner, || async {
+            panic!("{} panicked", "async body")
+        });
+
+        // Then:
+        assert_eq!(
+            Err(JunctionError::BodyPanicked(String::from(
+                "async body panicked"
+            ))),
+            get.recv()
+        );
+    }
+
+    #[test]
+    fn test_async_send_only_panic_is_handled() {
+        // Given:
+        let (tx, rx) = channel::<String>();
+        let tx = Mutex::new(tx);
+        let j = Junction::builder()
+            .panic_handler(move |payload| {
+                let message = payload.downcast::<&str>().unwrap().to_string();
+                tx.lock().unwrap().send(message).unwrap();
+            })
+            .build();
+        let a = j.send_channel::<()>();
+
+        j.when(&a)
+            .then_do_async(thread_spawner, |_| async { panic!("async send-only") });
+
+        // When:
+        a.send(()).unwrap();
+
+        // Then:
+        assert_eq!("async send-only", rx.recv().unwrap());
+    }
+
+    #[test]
+    fn test_async_body_without_reply() {
+        // Given:
+        let j = Junction::new();
+        let a = j.send_channel::<i32>();
+        let (tx, rx) = channel::<i32>();
+
+        j.when(&a).then_do_async(thread_spawner, move |x| {
+            let tx = tx.clone();
+
+            async move { tx.send(x).unwrap() }
+        });
+
+        // When:
+        a.send(1729).unwrap();
+
+        // Then:
+        assert_eq!(1729, rx.recv().unwrap());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_tokio_controller() {
+        // Given:
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let mut j = Junction::new_tokio();
+            let mut controller_handle = j.controller_handle().unwrap();
+            let a = j.send_channel::<i32>();
+            let get = j.recv_channel::<i32>();
+
+            j.when(&a).and_recv(&get).then_do(|v| v);
+
+            // When:
+            a.send(42).unwrap();
+            let value = get.recv_async().await.unwrap();
+
+            controller_handle.stop_async().await;
+
+            // Then:
+            assert_eq!(42, value);
+            assert!(controller_handle.thread().is_none());
+            assert!(a.send(1).is_err());
+        });
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_tokio_controller_stopped_on_drop() {
+        // Given:
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let j = Junction::new_tokio();
+            let a = j.send_channel::<i32>();
+
+            // When:
+            drop(j);
+
+            // Then:
+            let mut stopped = false;
+
+            for _ in 0..10_000 {
+                if a.send(1).is_err() {
+                    stopped = true;
+                    break;
+                }
+
+                tokio::task::yield_now().await;
+            }
+
+            assert!(stopped);
+        });
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_tokio_controller_on_current_thread_runtime() {
+        // Given:
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let j = Junction::new_tokio();
+            let a = j.send_channel::<i32>();
+            let get = j.recv_channel::<i32>();
+
+            j.when(&a).and_recv(&get).then_do(|v| v);
+
+            // When:
+            a.send(42).unwrap();
+            let value = get.recv_async().await.unwrap();
+
+            drop(j);
+
+            // Then:
+            assert_eq!(42, value);
+        });
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    #[should_panic]
+    fn test_stop_tokio_controller_on_current_thread_runtime() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let mut j = Junction::new_tokio();
+
+            j.controller_handle().unwrap().stop();
+        });
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_stop_tokio_controller_on_single_worker() {
+        // Given:
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .build()
+            .unwrap();
+        let (tx, rx) = channel::<bool>();
+
+        // When:
+        runtime.spawn(async move {
+            let mut j = Junction::new_tokio();
+            let a = j.send_channel::<i32>();
+
+            j.controller_handle().unwrap().stop();
+
+            tx.send(a.send(1).is_err()).unwrap();
+        });
+
+        // Then:
+        assert_eq!(Ok(true), rx.recv_timeout(Duration::from_secs(5)));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_drop_tokio_junction_on_single_worker() {
+        // Given:
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .build()
+            .unwrap();
+        let (tx, rx) = channel::<()>();
+
+        // When:
+        runtime.spawn(async move {
+            let j = Junction::new_tokio();
+            let a = j.send_channel::<i32>();
+            let get = j.recv_channel::<i32>();
+
+            j.when(&a).and_recv(&get).then_do(|v| v);
+            a.send(42).unwrap();
+            get.recv_async().await.unwrap();
+
+            drop(j);
+
+            tx.send(()).unwrap();
+        });
+
+        // Then:
+        assert!(rx.recv_timeout(Duration::from_secs(5)).is_ok());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_stop_async_thread_controller() {
+        // Given:
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .build()
+            .unwrap();
+        let mut j = Junction::new();
+        let mut controller_handle = j.controller_handle().unwrap();
+        let a = j.send_channel::<i32>();
+
+        // When:
+        runtime.block_on(controller_handle.stop_async());
+
+        // Then:
+        assert!(a.send(1).is_err());
+    }
+}
@@ -61,7 +61,7 @@
 //!     cell.when(&val).and_recv(&get).then_do(move |v| {
 //!         println!(">> val-get pattern fired with v={}!", v);
 //!
-//!         get_val.send(v.clone()).unwrap();
+//!         get_val.send(v).unwrap();
 //!
 //!         v
 //!     });
@@ -129,17 +129,33 @@
 //! channels to satisfy the conditions declared in the Join Patterns that will
 //! trigger an execution of their function body.
 //!
+//! The function bodies of fired Join Patterns run on a pool of 16 worker
+//! threads owned by their `Junction`, unless configured otherwise with a
+//! `JunctionBuilder`. A body waiting on a synchronous channel occupies its
+//! worker until the reply arrives. Should all workers wait on Join Patterns
+//! of their own `Junction`, none of these can run and the `Junction`
+//! deadlocks. Bodies that block like this call for a larger pool or for
+//! `executors::ThreadPerJob`, which spawns a thread for every firing.
+//!
 //! For more examples, visit the [`examples`](https://github.com/smueksch/rusty_junctions/tree/master/examples) folder in the [Rusty Junctions GitHub
 //! repository](https://github.com/smueksch/rusty_junctions).
 
 mod bag;
+mod builder;
+mod capacity;
 pub mod channels;
 mod controller;
 mod counter;
+pub mod errors;
+pub mod executors;
 mod function_transforms;
 mod inverted_index;
 mod junction;
+mod oneshot;
 pub mod patterns;
+pub mod policies;
+mod pool;
 pub mod types;
 
+pub use builder::JunctionBuilder;
 pub use junction::Junction;
@@ -0,0 +1,482 @@
+//! Single-use channel to send the reply of a fired Join Pattern back to the
+//! thread or task waiting on a synchronous channel.
+//!
+//! Unlike `std::sync::mpsc`, the receiving end can be waited on both by
+//! blocking the current thread and as a `Future`, so that synchronous
+//! channels can be used from plain threads and async code alike.
+//!
+//! The sending end can also fail the channel with a `JunctionError`, which
+//! the receiving end then returns instead of a value. Dropping it without
+//! sending reports `JunctionError::ControllerStopped`.
+//!
+//! The sending end offers a type-erased `Waiter` handle to the receiving
+//! end, which tells whether anybody is still waiting for the value. A thread
+//! blocked on the receiving end can also be handed a `Job` through it, which
+//! it runs before continuing to wait. This lets the function of a fired Join
+//! Pattern run on the thread waiting for its reply.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use super::errors::JunctionError;
+use super::executors::Job;
+
+/// Shared state of both ends of a oneshot channel.
+struct Inner<T> {
+    state: Mutex<State<T>>,
+    condvar: Condvar,
+}
+
+struct State<T> {
+    value: Option<T>,
+    job: Option<Job>,
+    waker: Option<Waker>,
+    sender_dropped: bool,
+    /// Error the `Sender` failed the channel with, if any.
+    error: Option<JunctionError>,
+    receiver_dropped: bool,
+}
+
+/// Sending end of a oneshot channel.
+pub(crate) struct Sender<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// Receiving end of a oneshot channel.
+pub(crate) struct Receiver<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// Create a new oneshot channel, returning both of its ends.
+pub(crate) fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let inner = Arc::new(Inner {
+        state: Mutex::new(State {
+            value: None,
+            job: None,
+            waker: None,
+            sender_dropped: false,
+            error: None,
+            receiver_dropped: false,
+        }),
+        condvar: Condvar::new(),
+    });
+
+    (
+        Sender {
+            inner: Arc::clone(&inner),
+        },
+        Receiver { inner },
+    )
+}
+
+impl<T> State<T> {
+    /// Return the error to report once the `Sender` has been dropped
+    /// without sending.
+    ///
+    /// This is the error the `Sender` failed the channel with, if any.
+    /// Otherwise, the `Junction` holding it has been stopped.
+    fn disconnected(&self) -> JunctionError {
+        self.error
+            .clone()
+            .unwrap_or(JunctionError::ControllerStopped)
+    }
+}
+
+impl<T> Inner<T> {
+    /// Wake up the `Receiver`, whether it blocks a thread or is polled.
+    fn notify(&self, state: &mut State<T>) {
+        self.condvar.notify_all();
+
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Type-erased handle to the `Receiver` of a oneshot channel.
+#[derive(Clone)]
+pub(crate) struct Waiter {
+    inner: Arc<dyn Waiting>,
+}
+
+/// Type-erased `Inner` of a oneshot channel, seen from the `Receiver`.
+trait Waiting: Send + Sync {
+    fn hand_over(&self, job: Job) -> Result<(), Job>;
+
+    fn is_abandoned(&self) -> bool;
+}
+
+impl<T> Waiting for Inner<T>
+where
+    T: Send,
+{
+    fn is_abandoned(&self) -> bool {
+        self.state.lock().unwrap().receiver_dropped
+    }
+
+    fn hand_over(&self, job: Job) -> Result<(), Job> {
+        let mut state = self.state.lock().unwrap();
+
+        if state.receiver_dropped {
+            return Err(job);
+        }
+
+        state.job = Some(job);
+        self.notify(&mut state);
+
+        Ok(())
+    }
+}
+
+impl Waiter {
+    /// Return `true` if the `Receiver` has been dropped, so that nobody is
+    /// waiting for the value anymore.
+    pub(crate) fn is_abandoned(&self) -> bool {
+        self.inner.is_abandoned()
+    }
+
+    /// Hand the `Job` to the thread blocked on the `Receiver` to run it.
+    ///
+    /// Only `Receiver`s waited on with `recv` run the `Job`s handed to them,
+    /// so this must not be used for those polled as a `Future`. Return the
+    /// `Job` back as an error if the `Receiver` has been dropped.
+    pub(crate) fn hand_over(&self, job: Job) -> Result<(), Job> {
+        self.inner.hand_over(job)
+    }
+}
+
+impl<T> Sender<T>
+where
+    T: Send + 'static,
+{
+    /// Return a `Waiter` handle to the `Receiver`.
+    pub(crate) fn waiter(&self) -> Waiter {
+        Waiter {
+            inner: Arc::clone(&self.inner) as Arc<dyn Waiting>,
+        }
+    }
+}
+
+impl<T> Sender<T> {
+    /// Send the value to the `Receiver`.
+    ///
+    /// Return the value back as an error if the `Receiver` has been dropped.
+    pub(crate) fn send(self, value: T) -> Result<(), T> {
+        let mut state = self.inner.state.lock().unwrap();
+
+        if state.receiver_dropped {
+            return Err(value);
+        }
+
+        state.value = Some(value);
+        self.inner.notify(&mut state);
+
+        Ok(())
+    }
+
+    /// Fail the channel, so that the `Receiver` returns the given error
+    /// instead of a value.
+    pub(crate) fn fail(self, error: JunctionError) {
+        self.inner.state.lock().unwrap().error = Some(error);
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    /// Let the `Receiver` know that no value will arrive if none was sent.
+    fn drop(&mut self) {
+        let mut state = self.inner.state.lock().unwrap();
+
+        state.sender_dropped = true;
+        self.inner.notify(&mut state);
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Block the current thread until the value arrives.
+    ///
+    /// `Job`s handed to the `Receiver` in the meantime are run on the
+    /// current thread. Return an error if the `Sender` has been dropped
+    /// without sending.
+    pub(crate) fn recv(self) -> Result<T, JunctionError> {
+        let mut state = self.inner.state.lock().unwrap();
+
+        loop {
+            if let Some(value) = state.value.take() {
+                return Ok(value);
+            }
+
+            if let Some(job) = state.job.take() {
+                drop(state);
+                job();
+                state = self.inner.state.lock().unwrap();
+
+                continue;
+            }
+
+            if state.sender_dropped {
+                return Err(state.disconnected());
+            }
+
+            state = self.inner.condvar.wait(state).unwrap();
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Block the current thread until the value arrives or the timeout
+    /// elapses.
+    ///
+    /// Works like `recv`, except that `JunctionError::Timeout` is returned
+    /// once `timeout` has elapsed without the value arriving. The `Receiver`
+    /// can then still be waited on again.
+    pub(crate) fn recv_timeout(&self, timeout: Duration) -> Result<T, JunctionError> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.inner.state.lock().unwrap();
+
+        loop {
+            if let Some(value) = state.value.take() {
+                return Ok(value);
+            }
+
+            if let Some(job) = state.job.take() {
+                drop(state);
+                job();
+                state = self.inner.state.lock().unwrap();
+
+                continue;
+            }
+
+            if state.sender_dropped {
+                return Err(state.disconnected());
+            }
+
+            let now = Instant::now();
+
+            if now >= deadline {
+                return Err(JunctionError::Timeout);
+            }
+
+            state = self
+                .inner
+                .condvar
+                .wait_timeout(state, deadline - now)
+                .unwrap()
+                .0;
+        }
+    }
+}
+
+impl<T> Future for Receiver<T> {
+    type Output = Result<T, JunctionError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.inner.state.lock().unwrap();
+
+        if let Some(value) = state.value.take() {
+            Poll::Ready(Ok(value))
+        } else if state.sender_dropped {
+            Poll::Ready(Err(state.disconnected()))
+        } else {
+            state.waker = Some(cx.waker().clone());
+
+            Poll::Pending
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    /// Let the `Sender` know that nobody is waiting for the value anymore.
+    fn drop(&mut self) {
+        self.inner.state.lock().unwrap().receiver_dropped = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::task::Wake;
+    use std::thread;
+
+    /// `Waker` recording whether it has been woken.
+    struct FlagWaker(AtomicBool);
+
+    impl Wake for FlagWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_recv_after_send() {
+        // Given:
+        let (tx, rx) = channel::<i32>();
+
+        // When:
+        tx.send(42).unwrap();
+
+        // Then:
+        assert_eq!(Ok(42), rx.recv());
+    }
+
+    #[test]
+    fn test_recv_blocks_until_send() {
+        // Given:
+        let (tx, rx) = channel::<i32>();
+
+        // When:
+        thread::spawn(move || tx.send(42).unwrap());
+
+        // Then:
+        assert_eq!(Ok(42), rx.recv());
+    }
+
+    #[test]
+    fn test_recv_dropped_sender() {
+        // Given:
+        let (tx, rx) = channel::<i32>();
+
+        // When:
+        drop(tx);
+
+        // Then:
+        assert_eq!(Err(JunctionError::ControllerStopped), rx.recv());
+    }
+
+    #[test]
+    fn test_recv_sender_dropped_while_panicking() {
+        // Given:
+        let (tx, rx) = channel::<i32>();
+
+        // When:
+        let _ = thread::spawn(move || {
+            let _tx = tx;
+
+            panic!("Dropping the Sender while panicking");
+        })
+        .join();
+
+        // Then:
+        assert_eq!(Err(JunctionError::ControllerStopped), rx.recv());
+    }
+
+    #[test]
+    fn test_recv_failed_sender() {
+        // Given:
+        let (tx, rx) = channel::<i32>();
+
+        // When:
+        tx.fail(JunctionError::BodyPanicked(String::from("failed")));
+
+        // Then:
+        assert_eq!(
+            Err(JunctionError::BodyPanicked(String::from("failed"))),
+            rx.recv()
+        );
+    }
+
+    #[test]
+    fn test_send_dropped_receiver() {
+        // Given:
+        let (tx, rx) = channel::<i32>();
+
+        // When:
+        drop(rx);
+
+        // Then:
+        assert_eq!(Err(42), tx.send(42));
+    }
+
+    #[test]
+    fn test_recv_timeout_elapses() {
+        // Given:
+        let (tx, rx) = channel::<i32>();
+
+        // When:
+        let timed_out = rx.recv_timeout(Duration::from_millis(10));
+        tx.send(42).unwrap();
+
+        // Then:
+        assert_eq!(Err(JunctionError::Timeout), timed_out);
+        assert_eq!(Ok(42), rx.recv());
+    }
+
+    #[test]
+    fn test_recv_timeout_after_send() {
+        // Given:
+        let (tx, rx) = channel::<i32>();
+
+        // When:
+        thread::spawn(move || tx.send(42).unwrap());
+
+        // Then:
+        assert_eq!(Ok(42), rx.recv_timeout(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_recv_runs_handed_over_job() {
+        // Given:
+        let (tx, rx) = channel::<thread::ThreadId>();
+        let waiter = tx.waiter();
+
+        // When:
+        thread::spawn(move || {
+            waiter
+                .hand_over(Box::new(move || tx.send(thread::current().id()).unwrap()))
+                .unwrap_or_else(|_| panic!("Receiver dropped"));
+        });
+
+        // Then:
+        assert_eq!(Ok(thread::current().id()), rx.recv());
+    }
+
+    #[test]
+    fn test_hand_over_dropped_receiver() {
+        // Given:
+        let (tx, rx) = channel::<i32>();
+        let waiter = tx.waiter();
+
+        // When:
+        drop(rx);
+
+        // Then:
+        assert!(waiter.hand_over(Box::new(|| {})).is_err());
+    }
+
+    #[test]
+    fn test_waiter_is_abandoned_once_receiver_dropped() {
+        // Given:
+        let (tx, rx) = channel::<i32>();
+        let waiter = tx.waiter();
+        let waiting = waiter.is_abandoned();
+
+        // When:
+        drop(rx);
+
+        // Then:
+        assert!(!waiting);
+        assert!(waiter.is_abandoned());
+    }
+
+    #[test]
+    fn test_poll_wakes_on_send() {
+        // Given:
+        let (tx, mut rx) = channel::<i32>();
+        let flag = Arc::new(FlagWaker(AtomicBool::new(false)));
+        let waker = Waker::from(Arc::clone(&flag));
+        let mut cx = Context::from_waker(&waker);
+
+        // When:
+        let first_poll = Pin::new(&mut rx).poll(&mut cx);
+        tx.send(42).unwrap();
+
+        // Then:
+        assert_eq!(Poll::Pending, first_poll);
+        assert!(flag.0.load(Ordering::SeqCst));
+        assert_eq!(Poll::Ready(Ok(42)), Pin::new(&mut rx).poll(&mut cx));
+    }
+}
@@ -1,1093 +1,683 @@
-//! Structs to implement different types of `JoinPattern`s.
+//! Structs to implement Join Patterns of arbitrary arity.
+//!
+//! A Join Pattern is constructed step by step, starting from one of the
+//! `when` functions of a `Junction`, which create a `PartialPattern` with a
+//! single channel. Further channels are added with `and`, `and_recv` and
+//! `and_bidir`, in any order, each of which returns a new `PartialPattern`
+//! that keeps track of the types involved. Calling one of the `then_do`
+//! functions completes the pattern and adds it to the `Junction`.
 
 use std::any::Any;
-use std::sync::mpsc::Sender;
-use std::thread;
-
-use super::channels::{
-    BidirChannel, RecvChannel, SendChannel, StrippedBidirChannel, StrippedRecvChannel,
-    StrippedSendChannel,
-};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use super::channels::{BidirChannel, RecvChannel, SendChannel};
+use super::errors::JunctionError;
+use super::executors::{Executor, Job, Spawner};
 use super::function_transforms;
-use super::types::{functions, ids, JoinPattern, Message, Packet};
-
-/// Structs for Join Patterns with one channel.
-pub mod unary {
-    use super::*;
+use super::types::tuples::{Append, Replies};
+use super::types::{functions, ids, FinishedGuard, Message, Packet, PacketSender};
+
+/// Partial Join Pattern of arbitrary arity.
+///
+/// The generic parameter `A` is a tuple of the types of values sent on the
+/// channels of this pattern, in declaration order, which are passed as
+/// arguments to the function of the full Join Pattern. `RecvChannel`s do not
+/// send a value and thus do not contribute to `A`.
+///
+/// The generic parameter `Rs` is a tuple of the types of values replied to
+/// the synchronous channels of this pattern, i.e. its `RecvChannel`s and
+/// `BidirChannel`s, in declaration order.
+///
+/// Patterns can be extended for as long as `A` and `Rs` contain at most
+/// `types::tuples::MAX_ARITY` elements each.
+pub struct PartialPattern<A, Rs> {
+    junction_id: ids::JunctionId,
+    channel_ids: Vec<ids::ChannelId>,
+    guard: Option<functions::GuardBox>,
+    options: Options,
+    sender: PacketSender,
+    types: PhantomData<(A, Rs)>,
+}
 
-    /**********************************
-     * Send Join Pattern Construction *
-     **********************************/
+/// Options of a Join Pattern, set while constructing it.
+#[derive(Default)]
+struct Options {
+    priority: i32,
+    executor: Option<Box<dyn Executor>>,
+    max_concurrency: Option<usize>,
+    run_on_caller: bool,
+}
 
-    /// `SendChannel` partial Join Pattern.
-    pub struct SendPartialPattern<T> {
+impl<A, Rs> PartialPattern<A, Rs> {
+    pub(crate) fn new(
         junction_id: ids::JunctionId,
-        send_channel: StrippedSendChannel<T>,
-        sender: Sender<Packet>,
-    }
-
-    impl<T> SendPartialPattern<T>
-    where
-        T: Any + Send,
-    {
-        pub(crate) fn new(
-            junction_id: ids::JunctionId,
-            send_channel: StrippedSendChannel<T>,
-            sender: Sender<Packet>,
-        ) -> SendPartialPattern<T> {
-            SendPartialPattern {
-                junction_id,
-                send_channel,
-                sender,
-            }
+        channel_id: ids::ChannelId,
+        sender: PacketSender,
+    ) -> PartialPattern<A, Rs> {
+        PartialPattern {
+            junction_id,
+            channel_ids: vec![channel_id],
+            guard: None,
+            options: Options::default(),
+            sender,
+            types: PhantomData,
         }
+    }
 
-        /// Create a binary partial Join Pattern with two send channels.
-        ///
-        /// Create a new binary partial Join Pattern that starts with the current
-        /// pattern and includes a new `SendChannel` after that.
-        ///
-        /// # Panics
-        ///
-        /// Panics if the supplied `SendChannel` does not carry the same
-        /// `JunctionID` as this `SendPartialPattern`, i.e. has not been created by
-        /// and is associated with the same `Junction`.
-        pub fn and<U>(self, send_channel: &SendChannel<U>) -> binary::SendPartialPattern<T, U>
-        where
-            U: Any + Send,
-        {
-            if send_channel.junction_id() == self.junction_id {
-                binary::SendPartialPattern::new(
-                    self.junction_id,
-                    self.send_channel,
-                    send_channel.strip(),
-                    self.sender,
-                )
-            } else {
+    /// Create a partial Join Pattern with the given channel added last.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `JunctionId` of the channel to be added does not match
+    /// the one of this `PartialPattern`, i.e. the channel has not been
+    /// created by and is associated with the same `Junction`.
+    fn extend<B, Ss>(
+        self,
+        channel_id: ids::ChannelId,
+        junction_id: ids::JunctionId,
+        channel_kind: &str,
+    ) -> PartialPattern<B, Ss> {
+        self.try_extend(channel_id, junction_id)
+            .unwrap_or_else(|_| {
                 panic!(
-                    "SendChannel and SendPartialPattern not associated \
-                     with same Junction! Please use a SendChannel created \
-                     using the same Junction as this partially complete Join \
-                     Pattern"
-                );
-            }
-        }
-
-        /// Create a binary partial Join Pattern with a send and receive channel.
-        ///
-        /// Create a new binary partial Join Pattern that starts with the current
-        /// pattern and includes a new `RecvChannel` after that.
-        ///
-        /// # Panics
-        ///
-        /// Panics if the supplied `RecvChannel` does not carry the same
-        /// `JunctionID` as this `SendPartialPattern`, i.e. has not been created by
-        /// and is associated with the same `Junction`.
-        pub fn and_recv<R>(self, recv_channel: &RecvChannel<R>) -> binary::RecvPartialPattern<T, R>
-        where
-            R: Any + Send,
-        {
-            if recv_channel.junction_id() == self.junction_id {
-                binary::RecvPartialPattern::new(
-                    self.send_channel,
-                    recv_channel.strip(),
-                    self.sender,
+                    "{} and PartialPattern not associated with same Junction! \
+                 Please use a {} created using the same Junction as this \
+                 partially complete Join Pattern",
+                    channel_kind, channel_kind
                 )
-            } else {
-                panic!(
-                    "RecvChannel and SendPartialPattern not associated \
-                     with same Junction! Please use a RecvChannel created \
-                     using the same Junction as this partially complete Join \
-                     Pattern"
-                );
-            }
-        }
+            })
+    }
 
-        /// Create a binary partial Join Pattern with a send and bidirectional channel.
-        ///
-        /// Create a new binary partial Join Pattern that starts with the current
-        /// pattern and includes a new `BidirChannel` after that.
-        ///
-        /// # Panics
-        ///
-        /// Panics if the supplied `BidirChannel` does not carry the same
-        /// `JunctionID` as this `SendPartialPattern`, i.e. has not been created by
-        /// and is associated with the same `Junction`.
-        pub fn and_bidir<U, R>(
-            self,
-            bidir_channel: &BidirChannel<U, R>,
-        ) -> binary::BidirPartialPattern<T, U, R>
-        where
-            U: Any + Send,
-            R: Any + Send,
-        {
-            if bidir_channel.junction_id() == self.junction_id {
-                binary::BidirPartialPattern::new(
-                    self.send_channel,
-                    bidir_channel.strip(),
-                    self.sender,
-                )
-            } else {
-                panic!(
-                    "BidirChannel and SendPartialPattern not associated \
-                     with same Junction! Please use a BidirChannel created \
-                     using the same Junction as this partially complete Join \
-                     Pattern"
-                );
-            }
+    /// Create a partial Join Pattern with the given channel added last.
+    ///
+    /// Return `JunctionError::WrongJunction` if the `JunctionId` of the
+    /// channel to be added does not match the one of this `PartialPattern`.
+    fn try_extend<B, Ss>(
+        mut self,
+        channel_id: ids::ChannelId,
+        junction_id: ids::JunctionId,
+    ) -> Result<PartialPattern<B, Ss>, JunctionError> {
+        if junction_id != self.junction_id {
+            return Err(JunctionError::WrongJunction);
         }
 
-        /// Create full Join Pattern and send request to add it to `Junction`.
-        ///
-        /// Create a full Join Pattern by taking the channels that are part of
-        /// the partial pattern and adding a function to be executed when there
-        /// is at least one message sent on each channel. Attempt to add the
-        /// Join Pattern to the `Junction` after creation.
-        ///
-        /// # Panics
-        ///
-        /// Panics if it was not possible to send the request to add the newly
-        /// create Join Pattern to the `Junction`.
-        pub fn then_do<F>(self, f: F)
-        where
-            F: Fn(T) -> () + Send + Clone + 'static,
-        {
-            let join_pattern = JoinPattern::UnarySend(SendJoinPattern::new(
-                self.send_channel.id(),
-                function_transforms::unary::transform_send(f),
-            ));
+        self.channel_ids.push(channel_id);
 
-            self.sender
-                .send(Packet::AddJoinPatternRequest { join_pattern })
-                .unwrap();
-        }
+        Ok(PartialPattern {
+            junction_id: self.junction_id,
+            channel_ids: self.channel_ids,
+            guard: self.guard,
+            options: self.options,
+            sender: self.sender,
+            types: PhantomData,
+        })
     }
 
-    /// `SendChannel` full Join Pattern.
-    pub struct SendJoinPattern {
-        channel_id: ids::ChannelId,
-        f: functions::unary::FnBox,
-    }
-
-    impl SendJoinPattern {
-        pub(crate) fn new(
-            channel_id: ids::ChannelId,
-            f: functions::unary::FnBox,
-        ) -> SendJoinPattern {
-            SendJoinPattern { channel_id, f }
-        }
-
-        /// Return the ID of the channel in this Join Pattern.
-        pub(crate) fn channel_id(&self) -> ids::ChannelId {
-            self.channel_id
-        }
+    /// Add a guard to the partial Join Pattern.
+    ///
+    /// If the partial Join Pattern already has a guard, both guards need to
+    /// be satisfied for the Join Pattern to fire.
+    fn add_guard(&mut self, guard: functions::GuardBox) {
+        self.guard = match self.guard.take() {
+            Some(previous) => Some(Box::new(move |values: &[&(dyn Any + Send)]| {
+                previous(values) && guard(values)
+            })),
+            None => Some(guard),
+        };
+    }
+
+    /// Send request to add the full Join Pattern to the `Junction`.
+    ///
+    /// If `once` is `true`, the `Junction` removes the Join Pattern as soon
+    /// as it has fired for the first time.
+    ///
+    /// Return a `PatternHandle` carrying the `JoinPatternId` of the new Join
+    /// Pattern. The ID is assigned up front rather than by the `Junction`,
+    /// so that adding a Join Pattern never waits for the `Controller`, which
+    /// may itself be running the function adding it.
+    ///
+    /// Should the `Junction` have been stopped, the request is dropped
+    /// together with the Join Pattern, which could never fire anyway.
+    fn add_join_pattern(self, f: functions::FnBox, once: bool) -> PatternHandle {
+        let join_pattern = JoinPattern::new(self.channel_ids, self.guard, self.options, f, once);
+        let join_pattern_id = ids::JoinPatternId::new();
 
-        /// Fire Join Pattern by running associated function in separate thread.
-        pub(crate) fn fire(&self, arg: Message) {
-            let f_clone = self.f.clone();
+        let _ = self.sender.send(Packet::AddJoinPatternRequest {
+            join_pattern_id,
+            join_pattern,
+        });
 
-            thread::spawn(move || {
-                (*f_clone)(arg);
-            });
-        }
+        PatternHandle::new(join_pattern_id, self.sender)
     }
+}
 
-    /*************************************
-     * Receive Join Pattern Construction *
-     *************************************/
+impl<A, Rs> PartialPattern<A, Rs> {
+    /// Set the priority of the Join Pattern.
+    ///
+    /// Whenever multiple Join Patterns of a `Junction` could fire at the same
+    /// time, one with the highest priority is chosen. Only among Join
+    /// Patterns of equal priority is the one fired that has not been fired
+    /// for the longest time. Join Patterns have a priority of `0` unless
+    /// specified otherwise.
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.options.priority = priority;
 
-    /// `RecvChannel` partial Join Pattern.
-    pub struct RecvPartialPattern<R> {
-        recv_channel: StrippedRecvChannel<R>,
-        sender: Sender<Packet>,
+        self
     }
 
-    impl<R> RecvPartialPattern<R>
+    /// Set the `Executor` to run the function of the Join Pattern with.
+    ///
+    /// Overrides the `Executor` of the `Junction` for this Join Pattern only.
+    /// To share an `Executor` between multiple Join Patterns, wrap it in an
+    /// `Arc` and pass a clone to each of them.
+    pub fn executor<E>(mut self, executor: E) -> Self
     where
-        R: Any + Send,
+        E: Executor + 'static,
     {
-        pub(crate) fn new(
-            recv_channel: StrippedRecvChannel<R>,
-            sender: Sender<Packet>,
-        ) -> RecvPartialPattern<R> {
-            RecvPartialPattern {
-                recv_channel,
-                sender,
-            }
-        }
+        self.options.executor = Some(Box::new(executor));
 
-        /// Create full Join Pattern and send request to add it to `Junction`.
-        ///
-        /// Create a full Join Pattern by taking the channels that are part of
-        /// the partial pattern and adding a function to be executed when there
-        /// is at least one message sent on each channel. Attempt to add the
-        /// Join Pattern to the `Junction` after creation.
-        ///
-        /// # Panics
-        ///
-        /// Panics if it was not possible to send the request to add the newly
-        /// create Join Pattern to the `Junction`.
-        pub fn then_do<F>(self, f: F)
-        where
-            F: Fn() -> R + Send + Clone + 'static,
-        {
-            let join_pattern = JoinPattern::UnaryRecv(RecvJoinPattern::new(
-                self.recv_channel.id(),
-                function_transforms::unary::transform_recv(f),
-            ));
-
-            self.sender
-                .send(Packet::AddJoinPatternRequest { join_pattern })
-                .unwrap();
-        }
+        self
     }
 
-    /// `RecvChannel` full Join Pattern.
+    /// Limit how many invocations of the Join Pattern's function may run at
+    /// the same time.
     ///
-    /// N.B.: While this struct appears to be a duplicate of `SendJoinPattern`
-    /// in terms of code, it is used to distinguish the capability of the
-    /// Join Pattern within the `Junction` through its type.
-    pub struct RecvJoinPattern {
-        channel_id: ids::ChannelId,
-        f: functions::unary::FnBox,
-    }
-
-    impl RecvJoinPattern {
-        pub(crate) fn new(
-            channel_id: ids::ChannelId,
-            f: functions::unary::FnBox,
-        ) -> RecvJoinPattern {
-            RecvJoinPattern { channel_id, f }
-        }
-
-        /// Return the ID of the channel in this Join Pattern.
-        pub(crate) fn channel_id(&self) -> ids::ChannelId {
-            self.channel_id
+    /// Once `max_concurrency` invocations are running, the `Junction` stops
+    /// firing the Join Pattern and leaves its `Message`s waiting until one
+    /// of them has finished. Note that the function of `then_do_async`
+    /// counts as finished once it has returned its `Future`, not once the
+    /// `Future` has completed. Join Patterns are not limited unless
+    /// specified otherwise.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_concurrency` is zero.
+    pub fn max_concurrency(mut self, max_concurrency: usize) -> Self {
+        if max_concurrency == 0 {
+            panic!("Join Pattern needs to be allowed at least one running invocation!");
         }
 
-        /// Fire Join Pattern by running associated function in separate thread.
-        pub(crate) fn fire(&self, return_sender: Message) {
-            let f_clone = self.f.clone();
+        self.options.max_concurrency = Some(max_concurrency);
 
-            thread::spawn(move || {
-                (*f_clone)(return_sender);
-            });
-        }
+        self
     }
 
-    /*******************************************
-     * Bidirectional Join Pattern Construction *
-     *******************************************/
+    /// Run the function of the Join Pattern on the thread waiting for its
+    /// reply.
+    ///
+    /// Whenever the Join Pattern fires with a `Message` from a thread blocked
+    /// in `RecvChannel::recv` or `BidirChannel::send_recv`, its function is
+    /// handed to that thread, which runs it before returning the reply. This
+    /// saves handing the function to an `Executor` and waking the waiting
+    /// thread afterwards. If the Join Pattern has multiple synchronous
+    /// channels, the function runs on the thread of the first of them.
+    ///
+    /// Otherwise, for instance when the reply is awaited with `recv_async`,
+    /// the function is run by the `Executor` as usual.
+    pub fn run_on_caller(mut self) -> Self {
+        self.options.run_on_caller = true;
 
-    /// Bidirectional channel partial Join Pattern.
-    pub struct BidirPartialPattern<T, R> {
-        bidir_channel: StrippedBidirChannel<T, R>,
-        sender: Sender<Packet>,
+        self
     }
 
-    impl<T, R> BidirPartialPattern<T, R>
+    /// Create a partial Join Pattern with an additional send channel.
+    ///
+    /// Create a new partial Join Pattern that starts with the current
+    /// pattern and includes a new `SendChannel` after that.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the supplied `SendChannel` does not carry the same
+    /// `JunctionID` as this `PartialPattern`, i.e. has not been created by
+    /// and is associated with the same `Junction`.
+    pub fn and<T>(self, send_channel: &SendChannel<T>) -> PartialPattern<A::Output, Rs>
     where
+        A: Append<T>,
         T: Any + Send,
-        R: Any + Send,
     {
-        pub(crate) fn new(
-            bidir_channel: StrippedBidirChannel<T, R>,
-            sender: Sender<Packet>,
-        ) -> BidirPartialPattern<T, R> {
-            BidirPartialPattern {
-                bidir_channel,
-                sender,
-            }
-        }
-
-        /// Create full Join Pattern and send request to add it to `Junction`.
-        ///
-        /// Create a full Join Pattern by taking the channels that are part of
-        /// the partial pattern and adding a function to be executed when there
-        /// is at least one message sent on each channel. Attempt to add the
-        /// Join Pattern to the `Junction` after creation.
-        ///
-        /// # Panics
-        ///
-        /// Panics if it was not possible to send the request to add the newly
-        /// create Join Pattern to the `Junction`.
-        pub fn then_do<F>(self, f: F)
-        where
-            F: Fn(T) -> R + Send + Clone + 'static,
-        {
-            let join_pattern = JoinPattern::UnaryBidir(BidirJoinPattern::new(
-                self.bidir_channel.id(),
-                function_transforms::unary::transform_bidir(f),
-            ));
-
-            self.sender
-                .send(Packet::AddJoinPatternRequest { join_pattern })
-                .unwrap();
-        }
-    }
-
-    /// `BidirChannel` full Join Pattern.
-    pub struct BidirJoinPattern {
-        channel_id: ids::ChannelId,
-        f: functions::unary::FnBox,
-    }
-
-    impl BidirJoinPattern {
-        pub(crate) fn new(
-            channel_id: ids::ChannelId,
-            f: functions::unary::FnBox,
-        ) -> BidirJoinPattern {
-            BidirJoinPattern { channel_id, f }
-        }
-
-        /// Return the ID of the channel in this Join Pattern.
-        pub(crate) fn channel_id(&self) -> ids::ChannelId {
-            self.channel_id
-        }
-
-        /// Fire Join Pattern by running associated function in separate thread.
-        pub(crate) fn fire(&self, arg_and_sender: Message) {
-            let f_clone = self.f.clone();
-
-            thread::spawn(move || {
-                (*f_clone)(arg_and_sender);
-            });
-        }
+        self.extend(send_channel.id(), send_channel.junction_id(), "SendChannel")
     }
-}
-
-/// Structs for Join Patterns with two channels.
-pub mod binary {
-    use super::*;
 
-    /*****************************************
-     * Send & Send Join Pattern Construction *
-     *****************************************/
-
-    /// Two `SendChannel` partial Join Pattern.
-    pub struct SendPartialPattern<T, U> {
-        junction_id: ids::JunctionId,
-        first_send_channel: StrippedSendChannel<T>,
-        second_send_channel: StrippedSendChannel<U>,
-        sender: Sender<Packet>,
-    }
-
-    impl<T, U> SendPartialPattern<T, U>
+    /// Create a partial Join Pattern with an additional send channel.
+    ///
+    /// Return `JunctionError::WrongJunction` if the supplied `SendChannel`
+    /// has not been created by and is associated with the same `Junction`.
+    pub fn try_and<T>(
+        self,
+        send_channel: &SendChannel<T>,
+    ) -> Result<PartialPattern<A::Output, Rs>, JunctionError>
     where
+        A: Append<T>,
         T: Any + Send,
-        U: Any + Send,
     {
-        pub(crate) fn new(
-            junction_id: ids::JunctionId,
-            first_send_channel: StrippedSendChannel<T>,
-            second_send_channel: StrippedSendChannel<U>,
-            sender: Sender<Packet>,
-        ) -> SendPartialPattern<T, U> {
-            SendPartialPattern {
-                junction_id,
-                first_send_channel,
-                second_send_channel,
-                sender,
-            }
-        }
-
-        /// Create a ternary partial `JoinPattern` with three send channels.
-        ///
-        /// Create a new ternary partial `JoinPattern` that starts with the current
-        /// pattern and includes a new `SendChannel` after that.
-        ///
-        /// # Panics
-        ///
-        /// Panics if the supplied `SendChannel` does not carry the same
-        /// `JunctionID` as this `SendPartialPattern`, i.e. has not been created by
-        /// and is associated with the same `Junction`.
-        pub fn and<V>(self, send_channel: &SendChannel<V>) -> ternary::SendPartialPattern<T, U, V>
-        where
-            V: Any + Send,
-        {
-            if send_channel.junction_id() == self.junction_id {
-                ternary::SendPartialPattern::new(
-                    self.junction_id,
-                    self.first_send_channel,
-                    self.second_send_channel,
-                    send_channel.strip(),
-                    self.sender,
-                )
-            } else {
-                panic!(
-                    "SendChannel and SendPartialPattern not associated \
-                     with same Junction! Please use a SendChannel created \
-                     using the same Junction as this partially complete Join \
-                     Pattern"
-                );
-            }
-        }
-
-        /// Create a ternary partial `JoinPattern` with two send and receive channel.
-        ///
-        /// Create a new ternary partial `JoinPattern` that starts with the current
-        /// pattern and includes a new `RecvChannel` after that.
-        ///
-        /// # Panics
-        ///
-        /// Panics if the supplied `RecvChannel` does not carry the same
-        /// `JunctionID` as this `SendPartialPattern`, i.e. has not been created by
-        /// and is associated with the same `Junction`.
-        pub fn and_recv<R>(
-            self,
-            recv_channel: &RecvChannel<R>,
-        ) -> ternary::RecvPartialPattern<T, U, R>
-        where
-            R: Any + Send,
-        {
-            if recv_channel.junction_id() == self.junction_id {
-                ternary::RecvPartialPattern::new(
-                    self.first_send_channel,
-                    self.second_send_channel,
-                    recv_channel.strip(),
-                    self.sender,
-                )
-            } else {
-                panic!(
-                    "RecvChannel and SendPartialPattern not associated \
-                     with same Junction! Please use a RecvChannel created \
-                     using the same Junction as this partially complete Join \
-                     Pattern"
-                );
-            }
-        }
-
-        /// Create a ternary partial `JoinPattern` with two send and bidirectional channel.
-        ///
-        /// Create a new ternary partial Join Pattern that starts with the current
-        /// pattern and includes a new `BidirChannel` after that.
-        ///
-        /// # Panics
-        ///
-        /// Panics if the supplied `BidirChannel` does not carry the same
-        /// `JunctionID` as this `SendPartialPattern`, i.e. has not been created by
-        /// and is associated with the same `Junction`.
-        pub fn and_bidir<V, R>(
-            self,
-            bidir_channel: &BidirChannel<V, R>,
-        ) -> ternary::BidirPartialPattern<T, U, V, R>
-        where
-            V: Any + Send,
-            R: Any + Send,
-        {
-            if bidir_channel.junction_id() == self.junction_id {
-                ternary::BidirPartialPattern::new(
-                    self.first_send_channel,
-                    self.second_send_channel,
-                    bidir_channel.strip(),
-                    self.sender,
-                )
-            } else {
-                panic!(
-                    "BidirChannel and SendPartialPattern not associated \
-                     with same Junction! Please use a BidirChannel created \
-                     using the same Junction as this partially complete Join \
-                     Pattern"
-                );
-            }
-        }
-
-        /// Create full Join Pattern and send request to add it to `Junction`.
-        ///
-        /// Create a full Join Pattern by taking the channels that are part of
-        /// the partial pattern and adding a function to be executed when there
-        /// is at least one message sent on each channel. Attempt to add the
-        /// Join Pattern to the `Junction` after creation.
-        ///
-        /// # Panics
-        ///
-        /// Panics if it was not possible to send the request to add the newly
-        /// create Join Pattern to the `Junction`.
-        pub fn then_do<F>(self, f: F)
-        where
-            F: Fn(T, U) -> () + Send + Clone + 'static,
-        {
-            let join_pattern = JoinPattern::BinarySend(SendJoinPattern::new(
-                self.first_send_channel.id(),
-                self.second_send_channel.id(),
-                function_transforms::binary::transform_send(f),
-            ));
-
-            self.sender
-                .send(Packet::AddJoinPatternRequest { join_pattern })
-                .unwrap();
-        }
-    }
-
-    /// `SendChannel` & `SendChannel` full Join Pattern.
-    pub struct SendJoinPattern {
-        first_send_channel_id: ids::ChannelId,
-        second_send_channel_id: ids::ChannelId,
-        f: functions::binary::FnBox,
-    }
-
-    impl SendJoinPattern {
-        pub(crate) fn new(
-            first_send_channel_id: ids::ChannelId,
-            second_send_channel_id: ids::ChannelId,
-            f: functions::binary::FnBox,
-        ) -> SendJoinPattern {
-            SendJoinPattern {
-                first_send_channel_id,
-                second_send_channel_id,
-                f,
-            }
-        }
-
-        /// Return the ID of the first `SendChannel` in this Join Pattern.
-        pub(crate) fn first_send_channel_id(&self) -> ids::ChannelId {
-            self.first_send_channel_id
-        }
-
-        /// Return the ID of the second `SendChannel` in this Join Pattern.
-        pub(crate) fn second_send_channel_id(&self) -> ids::ChannelId {
-            self.second_send_channel_id
-        }
-
-        /// Fire Join Pattern by running associated function in separate thread.
-        pub(crate) fn fire(&self, arg_1: Message, arg_2: Message) {
-            let f_clone = self.f.clone();
-
-            thread::spawn(move || {
-                (*f_clone)(arg_1, arg_2);
-            });
-        }
+        self.try_extend(send_channel.id(), send_channel.junction_id())
     }
 
-    /********************************************
-     * Send & Receive Join Pattern Construction *
-     ********************************************/
-
-    /// `SendChannel` & `RecvChannel` partial Join Pattern.
-    pub struct RecvPartialPattern<T, R> {
-        send_channel: StrippedSendChannel<T>,
-        recv_channel: StrippedRecvChannel<R>,
-        sender: Sender<Packet>,
-    }
-
-    impl<T, R> RecvPartialPattern<T, R>
+    /// Create a partial Join Pattern with an additional receive channel.
+    ///
+    /// Create a new partial Join Pattern that starts with the current
+    /// pattern and includes a new `RecvChannel` after that. The reply for
+    /// the new channel is added last to the replies of the pattern.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the supplied `RecvChannel` does not carry the same
+    /// `JunctionID` as this `PartialPattern`, i.e. has not been created by
+    /// and is associated with the same `Junction`.
+    pub fn and_recv<R>(self, recv_channel: &RecvChannel<R>) -> PartialPattern<A, Rs::Output>
     where
-        T: Any + Send,
+        Rs: Append<R>,
         R: Any + Send,
     {
-        pub(crate) fn new(
-            send_channel: StrippedSendChannel<T>,
-            recv_channel: StrippedRecvChannel<R>,
-            sender: Sender<Packet>,
-        ) -> RecvPartialPattern<T, R> {
-            RecvPartialPattern {
-                send_channel,
-                recv_channel,
-                sender,
-            }
-        }
-
-        /// Create full Join Pattern and send request to add it to `Junction`.
-        ///
-        /// Create a full Join Pattern by taking the channels that are part of
-        /// the partial pattern and adding a function to be executed when there
-        /// is at least one message sent on each channel. Attempt to add the
-        /// Join Pattern to the `Junction` after creation.
-        ///
-        /// # Panics
-        ///
-        /// Panics if it was not possible to send the request to add the newly
-        /// create Join Pattern to the `Junction`.
-        pub fn then_do<F>(self, f: F)
-        where
-            F: Fn(T) -> R + Send + Clone + 'static,
-        {
-            let join_pattern = JoinPattern::BinaryRecv(RecvJoinPattern::new(
-                self.send_channel.id(),
-                self.recv_channel.id(),
-                function_transforms::binary::transform_recv(f),
-            ));
-
-            self.sender
-                .send(Packet::AddJoinPatternRequest { join_pattern })
-                .unwrap();
-        }
+        self.extend(recv_channel.id(), recv_channel.junction_id(), "RecvChannel")
     }
 
-    /// `SendChannel` & `RecvChannel` full Join Pattern.
+    /// Create a partial Join Pattern with an additional receive channel.
     ///
-    /// N.B.: While this struct appears to be a duplicate of `SendJoinPattern`
-    /// in terms of code, it is used to distinguish the capability of the
-    /// Join Pattern within the `Junction` through its type.
-    pub struct RecvJoinPattern {
-        send_channel_id: ids::ChannelId,
-        recv_channel_id: ids::ChannelId,
-        f: functions::binary::FnBox,
-    }
-
-    impl RecvJoinPattern {
-        pub(crate) fn new(
-            send_channel_id: ids::ChannelId,
-            recv_channel_id: ids::ChannelId,
-            f: functions::binary::FnBox,
-        ) -> RecvJoinPattern {
-            RecvJoinPattern {
-                send_channel_id,
-                recv_channel_id,
-                f,
-            }
-        }
-
-        /// Return the ID of the `SendChannel` in this Join Pattern.
-        pub(crate) fn send_channel_id(&self) -> ids::ChannelId {
-            self.send_channel_id
-        }
-
-        /// Return the ID of the `RecvChannel` in this Join Pattern.
-        pub(crate) fn recv_channel_id(&self) -> ids::ChannelId {
-            self.recv_channel_id
-        }
-
-        /// Fire Join Pattern by running associated function in separate thread.
-        pub(crate) fn fire(&self, msg: Message, return_sender: Message) {
-            let f_clone = self.f.clone();
-
-            thread::spawn(move || {
-                (*f_clone)(msg, return_sender);
-            });
-        }
-    }
-
-    /**************************************************
-     * Send & Bidirectional Join Pattern Construction *
-     **************************************************/
-
-    /// `SendChannel` & `BidirChannel` partial Join Pattern.
-    pub struct BidirPartialPattern<T, U, R> {
-        send_channel: StrippedSendChannel<T>,
-        bidir_channel: StrippedBidirChannel<U, R>,
-        sender: Sender<Packet>,
+    /// Return `JunctionError::WrongJunction` if the supplied `RecvChannel`
+    /// has not been created by and is associated with the same `Junction`.
+    pub fn try_and_recv<R>(
+        self,
+        recv_channel: &RecvChannel<R>,
+    ) -> Result<PartialPattern<A, Rs::Output>, JunctionError>
+    where
+        Rs: Append<R>,
+        R: Any + Send,
+    {
+        self.try_extend(recv_channel.id(), recv_channel.junction_id())
     }
 
-    impl<T, U, R> BidirPartialPattern<T, U, R>
+    /// Create a partial Join Pattern with an additional bidirectional channel.
+    ///
+    /// Create a new partial Join Pattern that starts with the current
+    /// pattern and includes a new `BidirChannel` after that. The reply for
+    /// the new channel is added last to the replies of the pattern.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the supplied `BidirChannel` does not carry the same
+    /// `JunctionID` as this `PartialPattern`, i.e. has not been created by
+    /// and is associated with the same `Junction`.
+    pub fn and_bidir<T, R>(
+        self,
+        bidir_channel: &BidirChannel<T, R>,
+    ) -> PartialPattern<A::Output, Rs::Output>
     where
+        A: Append<T>,
+        Rs: Append<R>,
         T: Any + Send,
-        U: Any + Send,
         R: Any + Send,
     {
-        pub(crate) fn new(
-            send_channel: StrippedSendChannel<T>,
-            bidir_channel: StrippedBidirChannel<U, R>,
-            sender: Sender<Packet>,
-        ) -> BidirPartialPattern<T, U, R> {
-            BidirPartialPattern {
-                send_channel,
-                bidir_channel,
-                sender,
-            }
-        }
-
-        /// Create full Join Pattern and send request to add it to `Junction`.
-        ///
-        /// Create a full Join Pattern by taking the channels that are part of
-        /// the partial pattern and adding a function to be executed when there
-        /// is at least one message sent on each channel. Attempt to add the
-        /// Join Pattern to the `Junction` after creation.
-        ///
-        /// # Panics
-        ///
-        /// Panics if it was not possible to send the request to add the newly
-        /// create Join Pattern to the `Junction`.
-        pub fn then_do<F>(self, f: F)
-        where
-            F: Fn(T, U) -> R + Send + Clone + 'static,
-        {
-            let join_pattern = JoinPattern::BinaryBidir(BidirJoinPattern::new(
-                self.send_channel.id(),
-                self.bidir_channel.id(),
-                function_transforms::binary::transform_bidir(f),
-            ));
-
-            self.sender
-                .send(Packet::AddJoinPatternRequest { join_pattern })
-                .unwrap();
-        }
-    }
-
-    /// `SendChannel` & `BidirChannel` full Join Pattern.
-    pub struct BidirJoinPattern {
-        send_channel_id: ids::ChannelId,
-        bidir_channel_id: ids::ChannelId,
-        f: functions::binary::FnBox,
-    }
-
-    impl BidirJoinPattern {
-        pub(crate) fn new(
-            send_channel_id: ids::ChannelId,
-            bidir_channel_id: ids::ChannelId,
-            f: functions::binary::FnBox,
-        ) -> BidirJoinPattern {
-            BidirJoinPattern {
-                send_channel_id,
-                bidir_channel_id,
-                f,
-            }
-        }
-
-        /// Return the ID of the `SendChannel` in this Join Pattern.
-        pub(crate) fn send_channel_id(&self) -> ids::ChannelId {
-            self.send_channel_id
-        }
-
-        /// Return the ID of the `BidirChannel` in this Join Pattern.
-        pub(crate) fn bidir_channel_id(&self) -> ids::ChannelId {
-            self.bidir_channel_id
-        }
-
-        /// Fire Join Pattern by running associated function in separate thread.
-        pub(crate) fn fire(&self, arg_1: Message, arg_2_and_sender: Message) {
-            let f_clone = self.f.clone();
-
-            thread::spawn(move || {
-                (*f_clone)(arg_1, arg_2_and_sender);
-            });
-        }
+        self.extend(
+            bidir_channel.id(),
+            bidir_channel.junction_id(),
+            "BidirChannel",
+        )
     }
-}
-
-/// Structs for Join Patterns with two channels.
-pub mod ternary {
-    use super::*;
 
-    /****************************************
-     * Three Send Join Pattern Construction *
-     ****************************************/
-
-    /// Three `SendChannel` partial Join Pattern.
-    pub struct SendPartialPattern<T, U, V> {
-        junction_id: ids::JunctionId,
-        first_send_channel: StrippedSendChannel<T>,
-        second_send_channel: StrippedSendChannel<U>,
-        third_send_channel: StrippedSendChannel<V>,
-        sender: Sender<Packet>,
-    }
-
-    impl<T, U, V> SendPartialPattern<T, U, V>
+    /// Create a partial Join Pattern with an additional bidirectional channel.
+    ///
+    /// Return `JunctionError::WrongJunction` if the supplied `BidirChannel`
+    /// has not been created by and is associated with the same `Junction`.
+    pub fn try_and_bidir<T, R>(
+        self,
+        bidir_channel: &BidirChannel<T, R>,
+    ) -> Result<PartialPattern<A::Output, Rs::Output>, JunctionError>
     where
+        A: Append<T>,
+        Rs: Append<R>,
         T: Any + Send,
-        U: Any + Send,
-        V: Any + Send,
+        R: Any + Send,
     {
-        pub(crate) fn new(
-            junction_id: ids::JunctionId,
-            first_send_channel: StrippedSendChannel<T>,
-            second_send_channel: StrippedSendChannel<U>,
-            third_send_channel: StrippedSendChannel<V>,
-            sender: Sender<Packet>,
-        ) -> SendPartialPattern<T, U, V> {
-            SendPartialPattern {
-                junction_id,
-                first_send_channel,
-                second_send_channel,
-                third_send_channel,
-                sender,
-            }
-        }
+        self.try_extend(bidir_channel.id(), bidir_channel.junction_id())
+    }
+}
 
-        /// Create full Join Pattern and send request to add it to `Junction`.
-        ///
-        /// Create a full Join Pattern by taking the channels that are part of
-        /// the partial pattern and adding a function to be executed when there
-        /// is at least one message sent on each channel. Attempt to add the
-        /// Join Pattern to the `Junction` after creation.
-        ///
-        /// # Panics
-        ///
-        /// Panics if it was not possible to send the request to add the newly
-        /// create Join Pattern to the `Junction`.
-        pub fn then_do<F>(self, f: F)
+/// Implement `guard`, `then_do`, `then_do_once`, `then_do_mut` and
+/// `then_do_async` for partial Join Patterns with the given argument types.
+///
+/// All of them need to be implemented separately for each number of arguments so
+/// that the argument types of the closures passed to them can be inferred.
+macro_rules! impl_typed_steps {
+    ($($T:ident $t:ident),*) => {
+        impl<$($T,)* Rs> PartialPattern<($($T,)*), Rs>
         where
-            F: Fn(T, U, V) -> () + Send + Clone + 'static,
+            $($T: Any + Send,)*
+            Rs: Replies + 'static,
         {
-            let join_pattern = JoinPattern::TernarySend(SendJoinPattern::new(
-                self.first_send_channel.id(),
-                self.second_send_channel.id(),
-                self.third_send_channel.id(),
-                function_transforms::ternary::transform_send(f),
-            ));
-
-            self.sender
-                .send(Packet::AddJoinPatternRequest { join_pattern })
-                .unwrap();
-        }
-    }
-
-    /// Three `SendChannel` full `JoinPattern`.
-    pub struct SendJoinPattern {
-        first_send_channel_id: ids::ChannelId,
-        second_send_channel_id: ids::ChannelId,
-        third_send_channel_id: ids::ChannelId,
-        f: functions::ternary::FnBox,
-    }
+            /// Only fire the Join Pattern for messages satisfying a predicate.
+            ///
+            /// The predicate takes references to the values of the channels
+            /// added to the pattern so far, in declaration order. Whenever the
+            /// `Junction` considers firing the Join Pattern, it goes through
+            /// the combinations of available messages, oldest first, and picks
+            /// the first one that satisfies the predicate. Messages not picked
+            /// remain available for later firings.
+            ///
+            /// Calling `guard` multiple times requires all predicates to be
+            /// satisfied. As predicates may be evaluated many times and on the
+            /// control thread of the `Junction`, they should be cheap and free
+            /// of side effects. They may read state shared with the functions
+            /// of Join Patterns: after a firing, the `Junction` evaluates the
+            /// guards of all Join Patterns again. Changes made by functions
+            /// running on other threads are only noticed once the `Junction`
+            /// receives its next message, though.
+            ///
+            /// In the worst case, the predicate is evaluated once for every
+            /// combination of available messages, which grows exponentially
+            /// with the number of channels. A predicate that panics counts as
+            /// not satisfied by the messages it was given.
+            pub fn guard<G>(mut self, g: G) -> Self
+            where
+                G: Fn($(&$T),*) -> bool + Send + 'static,
+            {
+                self.add_guard(Box::new(move |values: &[&(dyn Any + Send)]| {
+                    #[allow(unused_mut, unused_variables)]
+                    let mut values = values.iter();
+
+                    g($(values.next().unwrap().downcast_ref::<$T>().unwrap()),*)
+                }));
+
+                self
+            }
 
-    impl SendJoinPattern {
-        pub(crate) fn new(
-            first_send_channel_id: ids::ChannelId,
-            second_send_channel_id: ids::ChannelId,
-            third_send_channel_id: ids::ChannelId,
-            f: functions::ternary::FnBox,
-        ) -> SendJoinPattern {
-            SendJoinPattern {
-                first_send_channel_id,
-                second_send_channel_id,
-                third_send_channel_id,
-                f,
+            /// Create full Join Pattern and send request to add it to `Junction`.
+            ///
+            /// Create a full Join Pattern by taking the channels that are part of
+            /// the partial pattern and adding a function to be executed when there
+            /// is at least one message sent on each channel. Attempt to add the
+            /// Join Pattern to the `Junction` after creation.
+            ///
+            /// The function returns the value to be replied to the synchronous
+            /// channel of the pattern, if there is one. Should the pattern
+            /// contain multiple synchronous channels, it returns a tuple with
+            /// one reply for each of them, in the order they were declared in.
+            ///
+            /// Return a `PatternHandle` that can be used to remove the Join
+            /// Pattern from the `Junction` again.
+            ///
+            /// Should the `Junction` have been stopped, the Join Pattern is
+            /// dropped right away, as it could never fire anyway.
+            pub fn then_do<F>(self, f: F) -> PatternHandle
+            where
+                F: Fn($($T),*) -> Rs::Output + Send + Clone + 'static,
+            {
+                let f = move |($($t,)*): ($($T,)*)| f($($t),*);
+
+                self.add_join_pattern(function_transforms::transform::<_, _, Rs>(f), false)
             }
-        }
 
-        /// Return the ID of the first `SendChannel` in this Join Pattern.
-        pub(crate) fn first_send_channel_id(&self) -> ids::ChannelId {
-            self.first_send_channel_id
-        }
+            /// Create full Join Pattern that fires at most once and add it to
+            /// `Junction`.
+            ///
+            /// Behaves like `then_do`, except that the `Junction` removes the
+            /// Join Pattern in the same step as firing it for the first time,
+            /// so that no second firing can happen. Since the function is
+            /// only ever called once, it may consume what it captures.
+            ///
+            /// Return a `PatternHandle` that can be used to remove the Join
+            /// Pattern from the `Junction` before it has fired.
+            ///
+            /// Should the `Junction` have been stopped, the Join Pattern is
+            /// dropped right away, as it could never fire anyway.
+            pub fn then_do_once<F>(self, f: F) -> PatternHandle
+            where
+                F: FnOnce($($T),*) -> Rs::Output + Send + 'static,
+            {
+                let f = Arc::new(Mutex::new(Some(f)));
+                let f = move |($($t,)*): ($($T,)*)| {
+                    let f = f.lock().unwrap().take().unwrap();
+
+                    f($($t),*)
+                };
+
+                self.add_join_pattern(function_transforms::transform::<_, _, Rs>(f), true)
+            }
 
-        /// Return the ID of the second `SendChannel` in this Join Pattern.
-        pub(crate) fn second_send_channel_id(&self) -> ids::ChannelId {
-            self.second_send_channel_id
-        }
+            /// Create full Join Pattern with a stateful function and add it to
+            /// `Junction`.
+            ///
+            /// Behaves like `then_do`, except that the function may mutate
+            /// what it captures, for instance state that would otherwise have
+            /// to be passed around on a channel of its own. The `Junction`
+            /// does not fire the Join Pattern again until the function has
+            /// finished, so that no two of its invocations ever overlap. This
+            /// overrides any `max_concurrency` set for the Join Pattern.
+            ///
+            /// Should the function panic, later invocations still run with
+            /// what it captures, in whatever state the panic left it.
+            ///
+            /// Return a `PatternHandle` that can be used to remove the Join
+            /// Pattern from the `Junction` again.
+            ///
+            /// Should the `Junction` have been stopped, the Join Pattern is
+            /// dropped right away, as it could never fire anyway.
+            pub fn then_do_mut<F>(mut self, f: F) -> PatternHandle
+            where
+                F: FnMut($($T),*) -> Rs::Output + Send + 'static,
+            {
+                // Invocations never overlap, so the lock is never contended.
+                // It is only poisoned by a panicking invocation, which must
+                // not keep all later ones from running.
+                let f = Arc::new(Mutex::new(f));
+                let f = move |($($t,)*): ($($T,)*)| {
+                    (*f.lock().unwrap_or_else(PoisonError::into_inner))($($t),*)
+                };
+
+                self.options.max_concurrency = Some(1);
+
+                self.add_join_pattern(function_transforms::transform::<_, _, Rs>(f), false)
+            }
 
-        /// Return the ID of the third `SendChannel` in this Join Pattern.
-        pub(crate) fn third_send_channel_id(&self) -> ids::ChannelId {
-            self.third_send_channel_id
+            /// Create full Join Pattern with an asynchronous function and add it
+            /// to `Junction`.
+            ///
+            /// Behaves like `then_do`, except that the function returns a
+            /// `Future`, which is run by the given `Spawner`. The output of the
+            /// `Future` is replied to the synchronous channels of the pattern,
+            /// if there are any. Waiting threads are only woken once the
+            /// `Future` has completed.
+            ///
+            /// Return a `PatternHandle` that can be used to remove the Join
+            /// Pattern from the `Junction` again.
+            ///
+            /// Should the `Junction` have been stopped, the Join Pattern is
+            /// dropped right away, as it could never fire anyway.
+            pub fn then_do_async<S, F, Fut>(self, spawner: S, f: F) -> PatternHandle
+            where
+                S: Spawner + 'static,
+                F: Fn($($T),*) -> Fut + Send + Clone + 'static,
+                Fut: Future<Output = Rs::Output> + Send + 'static,
+            {
+                let f = move |($($t,)*): ($($T,)*)| f($($t),*);
+
+                self.add_join_pattern(
+                    function_transforms::transform_async::<_, _, Rs, _>(f, Arc::new(spawner)),
+                    false,
+                )
+            }
         }
+    };
+}
 
-        /// Fire Join Pattern by running associated function in separate thread.
-        pub(crate) fn fire(&self, arg_1: Message, arg_2: Message, arg_3: Message) {
-            let f_clone = self.f.clone();
+impl_typed_steps!();
+impl_typed_steps!(T1 t1);
+impl_typed_steps!(T1 t1, T2 t2);
+impl_typed_steps!(T1 t1, T2 t2, T3 t3);
+impl_typed_steps!(T1 t1, T2 t2, T3 t3, T4 t4);
+impl_typed_steps!(T1 t1, T2 t2, T3 t3, T4 t4, T5 t5);
+impl_typed_steps!(T1 t1, T2 t2, T3 t3, T4 t4, T5 t5, T6 t6);
+impl_typed_steps!(T1 t1, T2 t2, T3 t3, T4 t4, T5 t5, T6 t6, T7 t7);
+impl_typed_steps!(T1 t1, T2 t2, T3 t3, T4 t4, T5 t5, T6 t6, T7 t7, T8 t8);
+impl_typed_steps!(T1 t1, T2 t2, T3 t3, T4 t4, T5 t5, T6 t6, T7 t7, T8 t8, T9 t9);
+impl_typed_steps!(T1 t1, T2 t2, T3 t3, T4 t4, T5 t5, T6 t6, T7 t7, T8 t8, T9 t9, T10 t10);
+impl_typed_steps!(T1 t1, T2 t2, T3 t3, T4 t4, T5 t5, T6 t6, T7 t7, T8 t8, T9 t9, T10 t10, T11 t11);
+impl_typed_steps!(T1 t1, T2 t2, T3 t3, T4 t4, T5 t5, T6 t6, T7 t7, T8 t8, T9 t9, T10 t10, T11 t11, T12 t12);
+
+/// Handle to a Join Pattern that has been added to a `Junction`.
+///
+/// The handle can be used to remove the Join Pattern from its `Junction` at
+/// any point, after which it will no longer fire. Messages that have already
+/// been sent to the `Junction` are not affected by the removal.
+pub struct PatternHandle {
+    join_pattern_id: ids::JoinPatternId,
+    sender: PacketSender,
+}
 
-            thread::spawn(move || {
-                (*f_clone)(arg_1, arg_2, arg_3);
-            });
+impl PatternHandle {
+    pub(crate) fn new(join_pattern_id: ids::JoinPatternId, sender: PacketSender) -> PatternHandle {
+        PatternHandle {
+            join_pattern_id,
+            sender,
         }
     }
 
-    /********************************************
-     * Send & Receive Join Pattern Construction *
-     ********************************************/
-
-    /// Two `SendChannel` & `RecvChannel` partial Join Pattern.
-    pub struct RecvPartialPattern<T, U, R> {
-        first_send_channel: StrippedSendChannel<T>,
-        second_send_channel: StrippedSendChannel<U>,
-        recv_channel: StrippedRecvChannel<R>,
-        sender: Sender<Packet>,
+    /// Return the ID of the Join Pattern within its `Junction`.
+    pub fn id(&self) -> ids::JoinPatternId {
+        self.join_pattern_id
     }
 
-    impl<T, U, R> RecvPartialPattern<T, U, R>
-    where
-        T: Any + Send,
-        U: Any + Send,
-        R: Any + Send,
-    {
-        pub(crate) fn new(
-            first_send_channel: StrippedSendChannel<T>,
-            second_send_channel: StrippedSendChannel<U>,
-            recv_channel: StrippedRecvChannel<R>,
-            sender: Sender<Packet>,
-        ) -> RecvPartialPattern<T, U, R> {
-            RecvPartialPattern {
-                first_send_channel,
-                second_send_channel,
-                recv_channel,
-                sender,
-            }
-        }
-
-        /// Create full Join Pattern and send request to add it to `Junction`.
-        ///
-        /// Create a full Join Pattern by taking the channels that are part of
-        /// the partial pattern and adding a function to be executed when there
-        /// is at least one message sent on each channel. Attempt to add the
-        /// Join Pattern to the `Junction` after creation.
-        ///
-        /// # Panics
-        ///
-        /// Panics if it was not possible to send the request to add the newly
-        /// create Join Pattern to the `Junction`.
-        pub fn then_do<F>(self, f: F)
-        where
-            F: Fn(T, U) -> R + Send + Clone + 'static,
-        {
-            let join_pattern = JoinPattern::TernaryRecv(RecvJoinPattern::new(
-                self.first_send_channel.id(),
-                self.second_send_channel.id(),
-                self.recv_channel.id(),
-                function_transforms::ternary::transform_recv(f),
-            ));
-
-            self.sender
-                .send(Packet::AddJoinPatternRequest { join_pattern })
-                .unwrap();
-        }
-    }
-
-    /// Two `SendChannel` & `RecvChannel` full `JoinPattern`.
+    /// Request the Join Pattern to be removed from its `Junction`.
     ///
-    /// N.B.: While this struct appears to be a duplicate of `SendJoinPattern`
-    /// in terms of code, it is used to distinguish the capability of the
-    /// Join Pattern within the `Junction` through its type.
-    pub struct RecvJoinPattern {
-        first_send_channel_id: ids::ChannelId,
-        second_send_channel_id: ids::ChannelId,
-        recv_channel_id: ids::ChannelId,
-        f: functions::ternary::FnBox,
+    /// Return `JunctionError::ControllerStopped` if the `Junction` has been
+    /// stopped, in which case the Join Pattern will not fire anymore either.
+    pub fn remove(self) -> Result<(), JunctionError> {
+        self.sender
+            .send(Packet::RemoveJoinPatternRequest {
+                join_pattern_id: self.join_pattern_id,
+            })
+            .map_err(|_| JunctionError::ControllerStopped)
     }
+}
 
-    impl RecvJoinPattern {
-        pub(crate) fn new(
-            first_send_channel_id: ids::ChannelId,
-            second_send_channel_id: ids::ChannelId,
-            recv_channel_id: ids::ChannelId,
-            f: functions::ternary::FnBox,
-        ) -> RecvJoinPattern {
-            RecvJoinPattern {
-                first_send_channel_id,
-                second_send_channel_id,
-                recv_channel_id,
-                f,
-            }
-        }
-
-        /// Return the ID of first `SendChannel` in this `JoinPattern`.
-        pub(crate) fn first_send_channel_id(&self) -> ids::ChannelId {
-            self.first_send_channel_id
-        }
-
-        /// Return the ID of second `SendChannel` in this `JoinPattern`.
-        pub(crate) fn second_send_channel_id(&self) -> ids::ChannelId {
-            self.second_send_channel_id
-        }
-
-        /// Return the ID of the `RecvChannel` in this `JoinPattern`.
-        pub(crate) fn recv_channel_id(&self) -> ids::ChannelId {
-            self.recv_channel_id
-        }
-
-        /// Fire `JoinPattern` by running associated function in separate thread.
-        pub(crate) fn fire(&self, arg_1: Message, arg_2: Message, return_sender: Message) {
-            let f_clone = self.f.clone();
+/// Full Join Pattern of arbitrary arity.
+///
+/// Stores the IDs of all channels involved in the pattern, in declaration
+/// order, alongside the function to run once a `Message` is available for
+/// each of them and an optional guard these `Message`s need to satisfy.
+/// Join Patterns with a higher priority are preferred when selecting which
+/// one to fire. Join Patterns marked as `once` are removed after their first
+/// firing. Join Patterns with an `Executor` of their own use it instead of
+/// the one of their `Junction`. Join Patterns with a maximum concurrency are
+/// not fired while that many of their invocations are still running.
+pub(crate) struct JoinPattern {
+    channel_ids: Vec<ids::ChannelId>,
+    guard: Option<functions::GuardBox>,
+    options: Options,
+    f: functions::FnBox,
+    once: bool,
+}
 
-            thread::spawn(move || {
-                (*f_clone)(arg_1, arg_2, return_sender);
-            });
+impl JoinPattern {
+    fn new(
+        channel_ids: Vec<ids::ChannelId>,
+        guard: Option<functions::GuardBox>,
+        options: Options,
+        f: functions::FnBox,
+        once: bool,
+    ) -> JoinPattern {
+        JoinPattern {
+            channel_ids,
+            guard,
+            options,
+            f,
+            once,
         }
     }
 
-    /**************************************************
-     * Send & Bidirectional Join Pattern Construction *
-     **************************************************/
-
-    /// `SendChannel` & `BidirChannel` partial Join Pattern.
-    pub struct BidirPartialPattern<T, U, V, R> {
-        first_send_channel: StrippedSendChannel<T>,
-        second_send_channel: StrippedSendChannel<U>,
-        bidir_channel: StrippedBidirChannel<V, R>,
-        sender: Sender<Packet>,
+    /// Return the priority of this Join Pattern.
+    pub(crate) fn priority(&self) -> i32 {
+        self.options.priority
     }
 
-    impl<T, U, V, R> BidirPartialPattern<T, U, V, R>
-    where
-        T: Any + Send,
-        U: Any + Send,
-        V: Any + Send,
-        R: Any + Send,
-    {
-        pub(crate) fn new(
-            first_send_channel: StrippedSendChannel<T>,
-            second_send_channel: StrippedSendChannel<U>,
-            bidir_channel: StrippedBidirChannel<V, R>,
-            sender: Sender<Packet>,
-        ) -> BidirPartialPattern<T, U, V, R> {
-            BidirPartialPattern {
-                first_send_channel,
-                second_send_channel,
-                bidir_channel,
-                sender,
-            }
-        }
-
-        /// Create full `JoinPattern` and send request to add it to `Junction`.
-        ///
-        /// Create a full Join Pattern by taking the channels that are part of
-        /// the partial pattern and adding a function to be executed when there
-        /// is at least one message sent on each channel. Attempt to add the
-        /// Join Pattern to the `Junction` after creation.
-        ///
-        /// # Panics
-        ///
-        /// Panics if it was not possible to send the request to add the newly
-        /// create Join Pattern to the `Junction`.
-        pub fn then_do<F>(self, f: F)
-        where
-            F: Fn(T, U, V) -> R + Send + Clone + 'static,
-        {
-            let join_pattern = JoinPattern::TernaryBidir(BidirJoinPattern::new(
-                self.first_send_channel.id(),
-                self.second_send_channel.id(),
-                self.bidir_channel.id(),
-                function_transforms::ternary::transform_bidir(f),
-            ));
+    /// Return the maximum number of invocations of this Join Pattern's
+    /// function that may run at the same time, if limited.
+    pub(crate) fn max_concurrency(&self) -> Option<usize> {
+        self.options.max_concurrency
+    }
 
-            self.sender
-                .send(Packet::AddJoinPatternRequest { join_pattern })
-                .unwrap();
-        }
+    /// Return `true` if this Join Pattern is to be removed after firing once.
+    pub(crate) fn is_once(&self) -> bool {
+        self.once
     }
 
-    /// Two `SendChannel` & `BidirChannel` full `JoinPattern`.
-    pub struct BidirJoinPattern {
-        first_send_channel_id: ids::ChannelId,
-        second_send_channel_id: ids::ChannelId,
-        bidir_channel_id: ids::ChannelId,
-        f: functions::ternary::FnBox,
+    /// Return the IDs of the channels in this Join Pattern.
+    ///
+    /// A channel appears as many times as it was added to the Join Pattern.
+    pub(crate) fn channel_ids(&self) -> &[ids::ChannelId] {
+        &self.channel_ids
     }
 
-    impl BidirJoinPattern {
-        pub(crate) fn new(
-            first_send_channel_id: ids::ChannelId,
-            second_send_channel_id: ids::ChannelId,
-            bidir_channel_id: ids::ChannelId,
-            f: functions::ternary::FnBox,
-        ) -> BidirJoinPattern {
-            BidirJoinPattern {
-                first_send_channel_id,
-                second_send_channel_id,
-                bidir_channel_id,
-                f,
+    /// Return `true` if the given `Message`s satisfy the guard of this Join
+    /// Pattern, or if it has no guard.
+    ///
+    /// The guard runs on the control thread, so a panic in it is caught
+    /// rather than taking down the `Controller`, and counts as the `Message`s
+    /// not satisfying it. The given `Message`s need to be in the same order
+    /// as the channels returned by `channel_ids`.
+    pub(crate) fn is_satisfied_by(&self, messages: &[&Message]) -> bool {
+        match &self.guard {
+            Some(guard) => {
+                let values: Vec<&(dyn Any + Send)> =
+                    messages.iter().filter_map(|msg| msg.value()).collect();
+
+                panic::catch_unwind(AssertUnwindSafe(|| guard(&values))).unwrap_or(false)
             }
+            None => true,
         }
+    }
 
-        /// Return the ID of first `SendChannel` in this Join Pattern.
-        pub(crate) fn first_send_channel_id(&self) -> ids::ChannelId {
-            self.first_send_channel_id
-        }
-
-        /// Return the ID of second `SendChannel` in this Join Pattern.
-        pub(crate) fn second_send_channel_id(&self) -> ids::ChannelId {
-            self.second_send_channel_id
-        }
-
-        /// Return the ID of the `BidirChannel` in this Join Pattern.
-        pub(crate) fn bidir_channel_id(&self) -> ids::ChannelId {
-            self.bidir_channel_id
-        }
-
-        /// Fire Join Pattern by running associated function in separate thread.
-        pub(crate) fn fire(&self, arg_1: Message, arg_2: Message, arg_3_and_sender: Message) {
-            let f_clone = self.f.clone();
-
-            thread::spawn(move || {
-                (*f_clone)(arg_1, arg_2, arg_3_and_sender);
-            });
-        }
+    /// Fire Join Pattern by running associated function on an `Executor`.
+    ///
+    /// The `Executor` of this Join Pattern is used if it has one, otherwise
+    /// the given default `Executor`. If the Join Pattern is to run on the
+    /// caller and one of the `Message`s comes from a blocked thread, the
+    /// function is handed to that thread instead.
+    ///
+    /// The function catches its own panics, so that they do not take down
+    /// the thread running it. Waiting callers receive
+    /// `JunctionError::BodyPanicked` instead of a reply. If no `Message` has
+    /// a caller waiting on it, the payload of the panic is handed to the
+    /// given `PanicHandler` instead, if any.
+    ///
+    /// The given `Message`s need to be in the same order as the channels
+    /// returned by `channel_ids`. The given `FinishedGuard`, if any, is
+    /// dropped as soon as the function has finished.
+    pub(crate) fn fire(
+        &self,
+        messages: Vec<Message>,
+        default_executor: &dyn Executor,
+        panic_handler: Option<&functions::PanicHandler>,
+        finished: Option<FinishedGuard>,
+    ) {
+        let waiter = if self.options.run_on_caller {
+            messages.iter().find_map(Message::job_waiter).cloned()
+        } else {
+            None
+        };
+
+        let panic_handler = panic_handler.cloned();
+
+        let f_clone = self.f.clone();
+        let job: Job = Box::new(move || {
+            let _finished = finished;
+
+            (*f_clone)(messages, panic_handler);
+        });
+
+        let job = match waiter {
+            Some(waiter) => match waiter.hand_over(job) {
+                Ok(()) => return,
+                Err(job) => job,
+            },
+            None => job,
+        };
+
+        let executor = self.options.executor.as_deref().unwrap_or(default_executor);
+
+        executor.execute(job);
     }
 }
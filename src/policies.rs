@@ -0,0 +1,287 @@
+//! Policies deciding which of several alive Join Patterns is fired.
+//!
+//! Whenever a new `Message` arrives at a `Junction`, multiple of its Join
+//! Patterns may be alive at the same time, i.e. have suitable `Message`s
+//! available for all of their channels. Only those with the highest priority
+//! are considered and a `FiringPolicy` then selects one of them to be fired.
+//!
+//! Each `Junction` has its own `FiringPolicy`, which by default is
+//! `LeastRecentlyFired`.
+
+use std::cmp::Ordering;
+
+use super::counter::Counter;
+use super::types::ids::JoinPatternId;
+
+/// Alive Join Pattern that may be selected to be fired.
+///
+/// Offers the information about the Join Pattern that `FiringPolicy`s base
+/// their decisions on.
+pub struct Candidate<'a> {
+    join_pattern_id: JoinPatternId,
+    last_fired: &'a Option<Counter>,
+    oldest_message: &'a Counter,
+}
+
+impl<'a> Candidate<'a> {
+    pub(crate) fn new(
+        join_pattern_id: JoinPatternId,
+        last_fired: &'a Option<Counter>,
+        oldest_message: &'a Counter,
+    ) -> Candidate<'a> {
+        Candidate {
+            join_pattern_id,
+            last_fired,
+            oldest_message,
+        }
+    }
+
+    /// Return the ID of the Join Pattern within its `Junction`.
+    pub fn join_pattern_id(&self) -> JoinPatternId {
+        self.join_pattern_id
+    }
+
+    /// Compare the order in which the Join Patterns were added to the `Junction`.
+    ///
+    /// The Join Pattern added first is ordered as less than the other.
+    pub fn cmp_declaration(&self, other: &Candidate) -> Ordering {
+        self.join_pattern_id.cmp(&other.join_pattern_id)
+    }
+
+    /// Compare when the Join Patterns were last fired.
+    ///
+    /// Rules for Order:
+    /// 1. If neither Join Pattern has been fired yet, they are viewed as
+    ///    equal in this ordering.
+    /// 2. If only one Join Pattern has not been fired yet, then that one is
+    ///    ordered as less than the other since having been fired at least
+    ///    once will always be a later point of firing than not having been
+    ///    fired yet.
+    /// 3. If both Join Patterns have been fired, the one fired earlier is
+    ///    ordered as less than the other.
+    pub fn cmp_last_fired(&self, other: &Candidate) -> Ordering {
+        match (self.last_fired, other.last_fired) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (Some(last_fired_1), Some(last_fired_2)) => last_fired_1.cmp(last_fired_2),
+        }
+    }
+
+    /// Compare how long the `Message`s the Join Patterns would consume have
+    /// been waiting.
+    ///
+    /// The Join Pattern whose oldest `Message` arrived first is ordered as
+    /// less than the other.
+    pub fn cmp_oldest_message(&self, other: &Candidate) -> Ordering {
+        self.oldest_message.cmp(other.oldest_message)
+    }
+}
+
+/// Strategy to select which of several alive Join Patterns is fired.
+pub trait FiringPolicy: Send {
+    /// Return the position of the `Candidate` to be fired.
+    ///
+    /// The given `Candidate`s are never empty and all have the same priority.
+    /// The returned position needs to be smaller than their number.
+    fn select(&mut self, candidates: &[Candidate]) -> usize;
+}
+
+/// Return the position of the first minimal `Candidate` under an ordering.
+fn position_of_min<'a, F>(candidates: &[Candidate<'a>], compare: F) -> usize
+where
+    F: Fn(&Candidate<'a>, &Candidate<'a>) -> Ordering,
+{
+    let mut min = 0;
+
+    for (position, candidate) in candidates.iter().enumerate().skip(1) {
+        if compare(candidate, &candidates[min]) == Ordering::Less {
+            min = position;
+        }
+    }
+
+    min
+}
+
+/// Fire the Join Pattern that was added to the `Junction` first.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeclarationOrder;
+
+impl FiringPolicy for DeclarationOrder {
+    fn select(&mut self, candidates: &[Candidate]) -> usize {
+        position_of_min(candidates, Candidate::cmp_declaration)
+    }
+}
+
+/// Fire the Join Pattern that has not been fired for the longest time.
+///
+/// In order to avoid certain scenarious in which one Join Pattern would
+/// block the execution of another, because it for instance has a subset of
+/// the other's channels, this policy selects from the Join Patterns that are
+/// alive simultaneously the one that has been waiting for the longest time.
+///
+/// Note that this procedure should ensure a certain form of *fairness*, by
+/// which if a Join Pattern has been alive an infinite amount of times, it
+/// will fire at least once. In practice, this should amount to each Join
+/// Pattern being incapable of getting deadlocked by others.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LeastRecentlyFired;
+
+impl FiringPolicy for LeastRecentlyFired {
+    fn select(&mut self, candidates: &[Candidate]) -> usize {
+        position_of_min(candidates, Candidate::cmp_last_fired)
+    }
+}
+
+/// Fire a Join Pattern chosen uniformly at random.
+///
+/// Uses a small pseudo-random number generator seeded by the user, so
+/// that the sequence of choices is reproducible.
+#[derive(Debug, Clone)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    /// Create a new `Random` policy from the given seed.
+    pub fn with_seed(seed: u64) -> Random {
+        Random { state: seed }
+    }
+
+    /// Return the next pseudo-random number, generated by SplitMix64.
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+        z ^ (z >> 31)
+    }
+}
+
+impl FiringPolicy for Random {
+    fn select(&mut self, candidates: &[Candidate]) -> usize {
+        (self.next_u64() % candidates.len() as u64) as usize
+    }
+}
+
+/// Fire the Join Pattern that consumes the `Message` that has been waiting
+/// for the longest time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OldestMessage;
+
+impl FiringPolicy for OldestMessage {
+    fn select(&mut self, candidates: &[Candidate]) -> usize {
+        position_of_min(candidates, Candidate::cmp_oldest_message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counter(value: usize) -> Counter {
+        let mut counter = Counter::default();
+
+        for _ in 0..value {
+            counter.increment();
+        }
+
+        counter
+    }
+
+    fn jp_id(value: usize) -> JoinPatternId {
+        let mut id = JoinPatternId::default();
+
+        for _ in 0..value {
+            id.increment();
+        }
+
+        id
+    }
+
+    #[test]
+    fn test_declaration_order_selects_first_added() {
+        // Given:
+        let (never, oldest) = (None, counter(1));
+        let candidates = vec![
+            Candidate::new(jp_id(2), &never, &oldest),
+            Candidate::new(jp_id(1), &never, &oldest),
+            Candidate::new(jp_id(3), &never, &oldest),
+        ];
+
+        // When:
+        let selected = DeclarationOrder.select(&candidates);
+
+        // Then:
+        assert_eq!(1, selected);
+    }
+
+    #[test]
+    fn test_least_recently_fired_prefers_never_fired() {
+        // Given:
+        let (fired, never, oldest) = (Some(counter(3)), None, counter(1));
+        let candidates = vec![
+            Candidate::new(jp_id(1), &fired, &oldest),
+            Candidate::new(jp_id(2), &never, &oldest),
+        ];
+
+        // When:
+        let selected = LeastRecentlyFired.select(&candidates);
+
+        // Then:
+        assert_eq!(1, selected);
+    }
+
+    #[test]
+    fn test_least_recently_fired_selects_earliest_fired() {
+        // Given:
+        let (later, earlier, oldest) = (Some(counter(5)), Some(counter(2)), counter(1));
+        let candidates = vec![
+            Candidate::new(jp_id(1), &later, &oldest),
+            Candidate::new(jp_id(2), &earlier, &oldest),
+        ];
+
+        // When:
+        let selected = LeastRecentlyFired.select(&candidates);
+
+        // Then:
+        assert_eq!(1, selected);
+    }
+
+    #[test]
+    fn test_oldest_message_selects_earliest_arrival() {
+        // Given:
+        let (never, newer, older) = (None, counter(7), counter(4));
+        let candidates = vec![
+            Candidate::new(jp_id(1), &never, &newer),
+            Candidate::new(jp_id(2), &never, &older),
+        ];
+
+        // When:
+        let selected = OldestMessage.select(&candidates);
+
+        // Then:
+        assert_eq!(1, selected);
+    }
+
+    #[test]
+    fn test_random_is_reproducible_and_in_range() {
+        // Given:
+        let (never, oldest) = (None, counter(1));
+        let candidates: Vec<Candidate> = (0..5)
+            .map(|i| Candidate::new(jp_id(i), &never, &oldest))
+            .collect();
+        let mut first = Random::with_seed(1729);
+        let mut second = Random::with_seed(1729);
+
+        // When:
+        let first_selections: Vec<usize> = (0..20).map(|_| first.select(&candidates)).collect();
+        let second_selections: Vec<usize> = (0..20).map(|_| second.select(&candidates)).collect();
+
+        // Then:
+        assert_eq!(first_selections, second_selections);
+        assert!(first_selections.iter().all(|&s| s < candidates.len()));
+    }
+}
@@ -0,0 +1,294 @@
+//! Bounded pool of reusable worker threads to run the function bodies of
+//! fired Join Patterns.
+//!
+//! By default, each `Controller` owns one `ThreadPool`, so that firing a
+//! Join Pattern does not require spawning a new OS thread. Workers are only
+//! spawned when a job arrives and no idle worker is available, up to the
+//! size of the pool. Once all workers are busy, further jobs wait in a queue
+//! until a worker becomes available again.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use super::executors::{Executor, Job};
+
+/// Number of worker threads in a pool if not specified otherwise.
+///
+/// Function bodies of Join Patterns often block, for instance while waiting
+/// on a synchronous channel, so this is deliberately not tied to the number
+/// of available CPUs.
+pub(crate) const DEFAULT_POOL_SIZE: usize = 16;
+
+/// Bounded pool of worker threads running `Job`s in the order they arrive.
+///
+/// Dropping the pool lets the workers finish all queued `Job`s, after which
+/// they terminate. The workers are not joined.
+pub struct ThreadPool {
+    size: usize,
+    /// Prefix of the names of the worker threads, unnamed if `None`.
+    thread_name: Option<String>,
+    /// Stack size of the worker threads in bytes, the default of
+    /// `std::thread` if `None`.
+    stack_size: Option<usize>,
+    spawned: AtomicUsize,
+    /// Number of workers that are waiting for a `Job` and have not been
+    /// reserved for one yet.
+    idle: Arc<AtomicUsize>,
+    sender: Mutex<Sender<Job>>,
+    receiver: Arc<Mutex<Receiver<Job>>>,
+}
+
+impl ThreadPool {
+    /// Create a new `ThreadPool` with at most `size` worker threads.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    pub fn new(size: usize) -> ThreadPool {
+        if size == 0 {
+            panic!("ThreadPool needs at least one worker thread!");
+        }
+
+        let (sender, receiver) = channel::<Job>();
+
+        ThreadPool {
+            size,
+            thread_name: None,
+            stack_size: None,
+            spawned: AtomicUsize::new(0),
+            idle: Arc::new(AtomicUsize::new(0)),
+            sender: Mutex::new(sender),
+            receiver: Arc::new(Mutex::new(receiver)),
+        }
+    }
+
+    /// Name the worker threads with the given prefix.
+    ///
+    /// Each worker is named by the prefix followed by `-worker-` and its
+    /// number within the pool, e.g. `prefix-worker-0`.
+    pub fn thread_name<S>(mut self, prefix: S) -> ThreadPool
+    where
+        S: Into<String>,
+    {
+        self.thread_name = Some(prefix.into());
+
+        self
+    }
+
+    /// Set the stack size of the worker threads in bytes.
+    pub fn stack_size(mut self, stack_size: usize) -> ThreadPool {
+        self.stack_size = Some(stack_size);
+
+        self
+    }
+
+    /// Reserve one idle worker for a new `Job`, if there is one.
+    fn reserve_idle_worker(&self) -> bool {
+        self.idle
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |idle| {
+                idle.checked_sub(1)
+            })
+            .is_ok()
+    }
+
+    /// Reserve room for a new worker, if the pool has not reached its size.
+    ///
+    /// Return the number of the new worker within the pool.
+    fn reserve_new_worker(&self) -> Option<usize> {
+        let size = self.size;
+
+        self.spawned
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |spawned| {
+                if spawned < size {
+                    Some(spawned + 1)
+                } else {
+                    None
+                }
+            })
+            .ok()
+    }
+
+    /// Spawn a new worker thread that runs `Job`s until the pool is dropped.
+    ///
+    /// A `Job` that panics does not stop the worker, which moves on to the
+    /// next `Job` instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the worker thread could not be spawned.
+    fn spawn_worker(&self, number: usize) {
+        let receiver = Arc::clone(&self.receiver);
+        let idle = Arc::clone(&self.idle);
+        let mut builder = thread::Builder::new();
+
+        if let Some(prefix) = &self.thread_name {
+            builder = builder.name(format!("{}-worker-{}", prefix, number));
+        }
+
+        if let Some(stack_size) = self.stack_size {
+            builder = builder.stack_size(stack_size);
+        }
+
+        builder
+            .spawn(move || loop {
+                let job = match receiver.lock().unwrap().recv() {
+                    Ok(job) => job,
+                    Err(_) => break,
+                };
+
+                // A panicking `Job` must not take down its worker, as the
+                // pool would never spawn a replacement for it.
+                let _ = panic::catch_unwind(AssertUnwindSafe(job));
+
+                idle.fetch_add(1, Ordering::AcqRel);
+            })
+            .unwrap();
+    }
+}
+
+impl Executor for ThreadPool {
+    /// Run the given `Job` on one of the worker threads.
+    ///
+    /// If no worker is idle and the pool has not reached its size yet, a
+    /// new worker is spawned for the `Job`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a new worker thread could not be spawned or the `Job` could
+    /// not be handed to the worker threads.
+    fn execute(&self, job: Job) {
+        if !self.reserve_idle_worker() {
+            if let Some(number) = self.reserve_new_worker() {
+                self.spawn_worker(number);
+            }
+        }
+
+        self.sender.lock().unwrap().send(job).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::mpsc::channel;
+    use std::sync::Barrier;
+    use std::thread::ThreadId;
+
+    #[test]
+    #[should_panic]
+    fn test_new_empty_pool() {
+        ThreadPool::new(0);
+    }
+
+    #[test]
+    fn test_execute_runs_job() {
+        // Given:
+        let pool = ThreadPool::new(1);
+        let (tx, rx) = channel::<i32>();
+
+        // When:
+        pool.execute(Box::new(move || tx.send(42).unwrap()));
+
+        // Then:
+        assert_eq!(42, rx.recv().unwrap());
+    }
+
+    #[test]
+    fn test_worker_survives_panicking_job() {
+        // Given:
+        let pool = ThreadPool::new(1);
+        let (tx, rx) = channel::<i32>();
+
+        // When:
+        pool.execute(Box::new(|| panic!("job panicked")));
+        pool.execute(Box::new(move || tx.send(42).unwrap()));
+
+        // Then:
+        assert_eq!(42, rx.recv().unwrap());
+        assert_eq!(1, pool.spawned.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn test_workers_are_named_with_prefix() {
+        // Given:
+        let pool = ThreadPool::new(1).thread_name("pool").stack_size(64 * 1024);
+        let (tx, rx) = channel::<Option<String>>();
+
+        // When:
+        pool.execute(Box::new(move || {
+            tx.send(thread::current().name().map(String::from)).unwrap()
+        }));
+
+        // Then:
+        assert_eq!(Some(String::from("pool-worker-0")), rx.recv().unwrap());
+    }
+
+    #[test]
+    fn test_workers_are_reused() {
+        // Given:
+        let pool = ThreadPool::new(1);
+        let (tx, rx) = channel::<ThreadId>();
+
+        // When:
+        for _ in 0..5 {
+            let tx = tx.clone();
+            pool.execute(Box::new(move || tx.send(thread::current().id()).unwrap()));
+        }
+
+        // Then:
+        let ids: Vec<ThreadId> = (0..5).map(|_| rx.recv().unwrap()).collect();
+
+        assert!(ids.iter().all(|&id| id == ids[0]));
+        assert_eq!(1, pool.spawned.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn test_spawns_workers_up_to_size_for_concurrent_jobs() {
+        // Given:
+        let pool = ThreadPool::new(3);
+        let barrier = Arc::new(Barrier::new(3));
+        let (tx, rx) = channel::<()>();
+
+        // When:
+        for _ in 0..3 {
+            let barrier = Arc::clone(&barrier);
+            let tx = tx.clone();
+
+            pool.execute(Box::new(move || {
+                barrier.wait();
+                tx.send(()).unwrap();
+            }));
+        }
+
+        // Then:
+        for _ in 0..3 {
+            rx.recv().unwrap();
+        }
+
+        assert_eq!(3, pool.spawned.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn test_jobs_queue_when_pool_is_full() {
+        // Given:
+        let pool = ThreadPool::new(2);
+        let (tx, rx) = channel::<i32>();
+
+        // When:
+        for i in 0..10 {
+            let tx = tx.clone();
+            pool.execute(Box::new(move || tx.send(i).unwrap()));
+        }
+
+        // Then:
+        let mut results: Vec<i32> = (0..10).map(|_| rx.recv().unwrap()).collect();
+        results.sort_unstable();
+
+        assert_eq!((0..10).collect::<Vec<i32>>(), results);
+        assert!(pool.spawned.load(Ordering::Acquire) <= 2);
+    }
+}
@@ -2,93 +2,313 @@
 //! crate.
 
 use std::any::Any;
-use std::sync::mpsc::Sender;
+use std::sync::mpsc::{SendError, Sender};
+use std::sync::Arc;
 use std::thread::{JoinHandle, Thread};
 
-use crate::patterns;
+#[cfg(feature = "tokio")]
+use tokio::sync::Notify;
 
-/// Shallow wrapper for a trait object using `Box` that can pass through thread
+use crate::capacity::Permit;
+use crate::errors::JunctionError;
+use crate::oneshot;
+use crate::patterns::JoinPattern;
+
+/// Type-erased value that can pass through thread boundaries.
+pub type Payload = Box<dyn Any + Send>;
+
+/// Shallow wrapper for trait objects using `Box` that can pass through thread
 /// boundaries.
-pub struct Message(Box<dyn Any + Send>);
+///
+/// A `Message` may carry a value, as sent by `SendChannel`s and
+/// `BidirChannel`s, as well as a return `Sender`, as sent by `RecvChannel`s
+/// and `BidirChannel`s, through which the value generated by a fired Join
+/// Pattern is sent back to the waiting thread. Alongside the return
+/// `Sender`, the `Message` carries a `Waiter` to check whether that thread
+/// is still waiting and, if it blocks while waiting, to hand the function of
+/// the Join Pattern to it. A `Message` sent on a bounded channel also
+/// carries the `Permit` for the space it takes up in the channel, until a
+/// Join Pattern is fired with it.
+pub(crate) struct Message {
+    /// ID of the `Message` if it carries a return `Sender`, so that the
+    /// waiting thread can withdraw it again.
+    id: Option<ids::MessageId>,
+    value: Option<Payload>,
+    return_sender: Option<Payload>,
+    waiter: Option<oneshot::Waiter>,
+    /// Whether the waiting thread runs `Job`s handed to it via `waiter`.
+    runs_jobs: bool,
+    permit: Option<Permit>,
+}
 
 impl Message {
+    /// Create a `Message` carrying only a value.
     pub(crate) fn new<T>(raw_value: T) -> Message
     where
         T: Any + Send,
     {
-        Message(Box::new(raw_value))
+        Message {
+            id: None,
+            value: Some(Box::new(raw_value)),
+            return_sender: None,
+            waiter: None,
+            runs_jobs: false,
+            permit: None,
+        }
+    }
+
+    /// Create a `Message` carrying only a return `Sender`.
+    pub(crate) fn from_return_sender<R>(return_sender: oneshot::Sender<R>) -> Message
+    where
+        R: Any + Send,
+    {
+        Message {
+            id: Some(ids::MessageId::new()),
+            value: None,
+            waiter: Some(return_sender.waiter()),
+            return_sender: Some(Box::new(return_sender)),
+            runs_jobs: false,
+            permit: None,
+        }
     }
 
-    /// Cast internal trait object to `Box<T>`.
-    pub(crate) fn downcast<T>(self) -> Result<Box<T>, Box<dyn Any + Send>>
+    /// Create a `Message` carrying both a value and a return `Sender`.
+    pub(crate) fn with_return_sender<T, R>(
+        raw_value: T,
+        return_sender: oneshot::Sender<R>,
+    ) -> Message
     where
         T: Any + Send,
+        R: Any + Send,
     {
-        self.0.downcast::<T>()
+        Message {
+            id: Some(ids::MessageId::new()),
+            value: Some(Box::new(raw_value)),
+            waiter: Some(return_sender.waiter()),
+            return_sender: Some(Box::new(return_sender)),
+            runs_jobs: false,
+            permit: None,
+        }
+    }
+
+    /// Let the `Message` take up the space of the given `Permit` until it
+    /// has been consumed or discarded.
+    pub(crate) fn holding(mut self, permit: Permit) -> Message {
+        self.permit = Some(permit);
+
+        self
+    }
+
+    /// Free the space the `Message` takes up in a bounded channel, if any.
+    ///
+    /// Used once the `Message` has been taken out of its channel, so that
+    /// the space is available again before its Join Pattern gets to run.
+    pub(crate) fn release_permit(&mut self) {
+        self.permit = None;
+    }
+
+    /// Mark the thread waiting for the reply to this `Message` as blocked,
+    /// so that Join Pattern functions can be handed to it.
+    pub(crate) fn running_jobs(mut self) -> Message {
+        self.runs_jobs = true;
+
+        self
+    }
+
+    /// Return the `Waiter` to hand Join Pattern functions to, if the
+    /// `Message` comes from a blocked thread.
+    pub(crate) fn job_waiter(&self) -> Option<&oneshot::Waiter> {
+        self.waiter.as_ref().filter(|_| self.runs_jobs)
+    }
+
+    /// Return `true` if the `Message` carries a return `Sender` whose
+    /// `Receiver` has been dropped, so no reply is waited for anymore.
+    pub(crate) fn is_abandoned(&self) -> bool {
+        self.waiter
+            .as_ref()
+            .is_some_and(oneshot::Waiter::is_abandoned)
+    }
+
+    /// Return the ID of the `Message`, if it carries a return `Sender`.
+    pub(crate) fn id(&self) -> Option<ids::MessageId> {
+        self.id
+    }
+
+    /// Return a reference to the value carried by the `Message`, if any.
+    pub(crate) fn value(&self) -> Option<&(dyn Any + Send)> {
+        self.value.as_deref()
+    }
+
+    /// Split the `Message` into its value and return `Sender`, if available.
+    pub(crate) fn into_parts(self) -> (Option<Payload>, Option<Payload>) {
+        (self.value, self.return_sender)
     }
 }
 
 /// Standardized packet to be used to send messages of various types on the
 /// channels of a Junction.
-pub enum Packet {
+pub(crate) enum Packet {
     /// General message send from channel identified by `channel_id`.
     Message {
         channel_id: ids::ChannelId,
         msg: Message,
     },
-    /// Request a new channel ID from the Junction so a new channel can be
-    /// constructed. New ID will be sent back through `return_sender`.
-    NewChannelIdRequest {
-        return_sender: Sender<ids::ChannelId>,
+    /// Message sent from channel identified by `channel_id` that is only to
+    /// be kept if a Join Pattern fires with it right away. Whether one did
+    /// is sent back through `return_sender`.
+    TryMessage {
+        channel_id: ids::ChannelId,
+        msg: Message,
+        return_sender: Sender<bool>,
+    },
+    /// Request adding a new Join Pattern identified by `join_pattern_id` to
+    /// the Junction.
+    AddJoinPatternRequest {
+        join_pattern_id: ids::JoinPatternId,
+        join_pattern: JoinPattern,
+    },
+    /// Request removing the Join Pattern identified by `join_pattern_id` from
+    /// the Junction.
+    RemoveJoinPatternRequest { join_pattern_id: ids::JoinPatternId },
+    /// Request withdrawing the `Message` identified by `message_id` from
+    /// channel `channel_id` before any Join Pattern consumes it. Whether it
+    /// was still available and thus withdrawn is sent back through
+    /// `return_sender`.
+    WithdrawMessageRequest {
+        channel_id: ids::ChannelId,
+        message_id: ids::MessageId,
+        return_sender: Sender<bool>,
     },
-    /// Request adding a new Join Pattern to the Junction.
-    AddJoinPatternRequest { join_pattern: JoinPattern },
+    /// Notify the Junction that the function of a fired Join Pattern
+    /// identified by `join_pattern_id` has finished running.
+    JoinPatternFinished { join_pattern_id: ids::JoinPatternId },
     /// Request the internal control thread managing the `Message`s to shut down.
     ShutDownRequest,
 }
 
-/// Enum defining all Join Patterns that can be added to a Junction using the
-/// `AddJoinPatternRequest` in a `Packet`.
-pub enum JoinPattern {
-    /// Single channel Join Pattern.
-    UnarySend(patterns::unary::SendJoinPattern),
-    /// Single `RecvChannel` Join Pattern.
-    UnaryRecv(patterns::unary::RecvJoinPattern),
-    /// Single `BidirChannel` Join Pattern.
-    UnaryBidir(patterns::unary::BidirJoinPattern),
-    /// Two `SendChannel` Join Pattern.
-    BinarySend(patterns::binary::SendJoinPattern),
-    /// `SendChannel` and `RecvChannel` Join Pattern.
-    BinaryRecv(patterns::binary::RecvJoinPattern),
-    /// `SendChannel` and `BidirChannel` Join Pattern.
-    BinaryBidir(patterns::binary::BidirJoinPattern),
-    /// Three `SendChannel` Join Pattern.
-    TernarySend(patterns::ternary::SendJoinPattern),
-    /// Two `SendChannel` and `RecvChannel` Join Pattern.
-    TernaryRecv(patterns::ternary::RecvJoinPattern),
-    /// Two `SendChannel` and `BidirChannel` Join Pattern.
-    TernaryBidir(patterns::ternary::BidirJoinPattern),
+/// Sending end of the channel through which a `Controller` receives `Packet`s.
+///
+/// If the `Controller` runs as an async task, it is woken up after each
+/// `Packet` sent.
+#[derive(Clone)]
+pub(crate) struct PacketSender {
+    sender: Sender<Packet>,
+    #[cfg(feature = "tokio")]
+    notify: Option<Arc<Notify>>,
+}
+
+impl PacketSender {
+    /// Create a `PacketSender` for a `Controller` running in its own thread.
+    pub(crate) fn new(sender: Sender<Packet>) -> PacketSender {
+        PacketSender {
+            sender,
+            #[cfg(feature = "tokio")]
+            notify: None,
+        }
+    }
+
+    /// Create a `PacketSender` for a `Controller` running as an async task,
+    /// waiting to be notified through `notify` of new `Packet`s.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn with_notify(sender: Sender<Packet>, notify: Arc<Notify>) -> PacketSender {
+        PacketSender {
+            sender,
+            notify: Some(notify),
+        }
+    }
+
+    /// Send the `Packet` to the `Controller`.
+    pub(crate) fn send(&self, packet: Packet) -> Result<(), SendError<Packet>> {
+        self.sender.send(packet)?;
+        self.wake_controller();
+
+        Ok(())
+    }
+
+    /// Wake the `Controller` if it runs as an async task.
+    #[cfg(feature = "tokio")]
+    fn wake_controller(&self) {
+        if let Some(notify) = &self.notify {
+            notify.notify_one();
+        }
+    }
+
+    /// Wake the `Controller` if it runs as an async task.
+    #[cfg(not(feature = "tokio"))]
+    fn wake_controller(&self) {}
+}
+
+/// Guard sending `Packet::JoinPatternFinished` to the `Controller` once
+/// dropped.
+///
+/// Moved into the `Job` of a fired Join Pattern, so that the `Controller` is
+/// notified as soon as its function has finished, whether it returned or
+/// panicked.
+pub(crate) struct FinishedGuard {
+    join_pattern_id: ids::JoinPatternId,
+    sender: PacketSender,
+}
+
+impl FinishedGuard {
+    pub(crate) fn new(join_pattern_id: ids::JoinPatternId, sender: PacketSender) -> FinishedGuard {
+        FinishedGuard {
+            join_pattern_id,
+            sender,
+        }
+    }
+}
+
+impl Drop for FinishedGuard {
+    fn drop(&mut self) {
+        // The `Controller` may already have been stopped, in which case
+        // nobody is interested in the notification anymore.
+        let _ = self.sender.send(Packet::JoinPatternFinished {
+            join_pattern_id: self.join_pattern_id,
+        });
+    }
 }
 
 /// Handle to a `Junction`'s underlying `Controller`.
 ///
 /// This struct carries a `JoinHandle` to the thread that the `Controller` of
-/// a `Junction` is running in. It allows for the `Controller` and its thread
-/// to be stopped gracefully at any point.
+/// a `Junction` is running in or, if it runs as an async task, a way to wait
+/// for that task to finish. It allows for the `Controller` to be stopped
+/// gracefully at any point.
 pub struct ControllerHandle {
-    sender: Sender<Packet>,
+    sender: PacketSender,
     control_thread_handle: Option<JoinHandle<()>>,
+    #[cfg(feature = "tokio")]
+    control_task_stopped: Option<crate::oneshot::Receiver<()>>,
 }
 
 impl ControllerHandle {
-    pub(crate) fn new(sender: Sender<Packet>, handle: JoinHandle<()>) -> ControllerHandle {
+    pub(crate) fn new(sender: PacketSender, handle: JoinHandle<()>) -> ControllerHandle {
         ControllerHandle {
             sender,
             control_thread_handle: Some(handle),
+            #[cfg(feature = "tokio")]
+            control_task_stopped: None,
+        }
+    }
+
+    /// Create a handle to a `Controller` running as an async task.
+    ///
+    /// The given `Receiver` needs to be disconnected once the task finished.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn from_task(
+        sender: PacketSender,
+        control_task_stopped: crate::oneshot::Receiver<()>,
+    ) -> ControllerHandle {
+        ControllerHandle {
+            sender,
+            control_thread_handle: None,
+            control_task_stopped: Some(control_task_stopped),
         }
     }
 
     /// Extracts a handle to the underlying thread.
+    ///
+    /// Returns `None` if the `Controller` runs as an async task.
     pub fn thread(&self) -> Option<&Thread> {
         match &self.control_thread_handle {
             Some(h) => Some(h.thread()),
@@ -98,13 +318,126 @@ impl ControllerHandle {
 
     /// Request the `Controller` to stop gracefully, then join its thread.
     ///
+    /// If the `Controller` runs as an async task, block the current thread
+    /// until the task has finished instead. Prefer `stop_async` within async
+    /// code in that case. On a multi-thread tokio runtime, the worker thread
+    /// blocked this way hands its other tasks over to a new thread first.
+    ///
     /// # Panics
     ///
     /// Panics if it was unable to send shut-down request to the control thread.
+    /// Also panics if the `Controller` runs as an async task and the current
+    /// thread drives a current-thread tokio runtime, as the task could then
+    /// never finish while the thread is blocked.
     pub fn stop(&mut self) {
+        if self.blocks_control_task() {
+            panic!(
+                "Cannot block on a Controller running on the current-thread \
+                 runtime! Please use stop_async instead."
+            );
+        }
+
         self.sender.send(Packet::ShutDownRequest).unwrap();
 
-        self.control_thread_handle.take().unwrap().join().unwrap();
+        match self.control_thread_handle.take() {
+            Some(handle) => handle.join().unwrap(),
+            None => self.wait_for_control_task(),
+        }
+    }
+
+    /// Request the `Controller` to stop gracefully and wait until it has
+    /// stopped without blocking the current thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if it was unable to send shut-down request to the `Controller`.
+    #[cfg(feature = "tokio")]
+    pub async fn stop_async(&mut self) {
+        self.sender.send(Packet::ShutDownRequest).unwrap();
+
+        match self.control_thread_handle.take() {
+            Some(handle) => tokio::task::spawn_blocking(move || handle.join().unwrap())
+                .await
+                .unwrap(),
+            None => {
+                let _ = self.control_task_stopped.take().unwrap().await;
+            }
+        }
+    }
+
+    /// Stop the `Controller` as part of dropping its `Junction`.
+    ///
+    /// Works like `stop`, except that if the `Controller` runs as an async
+    /// task and the current thread is part of a tokio runtime, the task is
+    /// only requested to stop and left to finish on its own, so that no
+    /// thread of the runtime is blocked. A failure to send the request means
+    /// that the `Controller` has stopped already.
+    pub(crate) fn stop_on_drop(&mut self) {
+        if self.runs_beside_control_task() {
+            let _ = self.sender.send(Packet::ShutDownRequest);
+        } else {
+            self.stop();
+        }
+    }
+
+    /// Return `true` if the `Controller` runs as an async task that cannot
+    /// make progress while the current thread is blocked, i.e. the current
+    /// thread drives a current-thread tokio runtime.
+    #[cfg(feature = "tokio")]
+    fn blocks_control_task(&self) -> bool {
+        use tokio::runtime::{Handle, RuntimeFlavor};
+
+        self.control_task_stopped.is_some()
+            && Handle::try_current()
+                .is_ok_and(|handle| handle.runtime_flavor() == RuntimeFlavor::CurrentThread)
+    }
+
+    /// Return `true` if the `Controller` runs as an async task that cannot
+    /// make progress while the current thread is blocked. Without async
+    /// tasks, this is never the case.
+    #[cfg(not(feature = "tokio"))]
+    fn blocks_control_task(&self) -> bool {
+        false
+    }
+
+    /// Return `true` if the `Controller` runs as an async task and the
+    /// current thread is part of a tokio runtime, whatever its flavor.
+    #[cfg(feature = "tokio")]
+    fn runs_beside_control_task(&self) -> bool {
+        self.control_task_stopped.is_some() && tokio::runtime::Handle::try_current().is_ok()
+    }
+
+    /// Return `true` if the `Controller` runs as an async task and the
+    /// current thread is part of a tokio runtime. Without async tasks, this
+    /// is never the case.
+    #[cfg(not(feature = "tokio"))]
+    fn runs_beside_control_task(&self) -> bool {
+        false
+    }
+
+    /// Block the current thread until the control task has finished.
+    ///
+    /// Should the current thread be a worker of a multi-thread runtime, its
+    /// other tasks, possibly including the control task, are moved to a new
+    /// worker thread while it blocks.
+    #[cfg(feature = "tokio")]
+    fn wait_for_control_task(&mut self) {
+        let control_task_stopped = self.control_task_stopped.take().unwrap();
+
+        tokio::task::block_in_place(move || {
+            let _ = control_task_stopped.recv();
+        });
+    }
+
+    /// Block the current thread until the control task has finished.
+    ///
+    /// # Panics
+    ///
+    /// Always panics, since without async tasks, the `Controller` can only
+    /// have stopped already.
+    #[cfg(not(feature = "tokio"))]
+    fn wait_for_control_task(&mut self) {
+        panic!("Controller has already been stopped!");
     }
 }
 
@@ -113,128 +446,282 @@ impl ControllerHandle {
 pub mod functions {
     use super::*;
 
-    /// Types and Traits for functions which take one argument.
-    pub mod unary {
-        use super::*;
+    /// Trait to allow boxed up functions that take the `Message`s of all
+    /// channels in a Join Pattern and return nothing to be cloned.
+    ///
+    /// Alongside the `Message`s, the functions take the `PanicHandler` for
+    /// panics that no caller is waiting on, if any.
+    pub(crate) trait FnBoxClone: Fn(Vec<Message>, Option<PanicHandler>) + Send {
+        fn clone_box(&self) -> Box<dyn FnBoxClone>;
+    }
 
-        /// Trait to allow boxed up functions that take one `Message` and return
-        /// nothing to be cloned.
-        pub trait FnBoxClone: Fn(Message) -> () + Send {
-            fn clone_box(&self) -> Box<dyn FnBoxClone>;
+    impl<F> FnBoxClone for F
+    where
+        F: Fn(Vec<Message>, Option<PanicHandler>) + Send + Clone + 'static,
+    {
+        /// Proxy function to be able to implement the `Clone` trait on
+        /// boxed up functions that take `Message`s and return nothing.
+        fn clone_box(&self) -> Box<dyn FnBoxClone> {
+            Box::new(self.clone())
         }
+    }
 
-        impl<F> FnBoxClone for F
-        where
-            F: Fn(Message) -> () + Send + Clone + 'static,
-        {
-            /// Proxy function to be able to implement the `Clone` trait on
-            /// boxed up functions that take one `Message` and return nothing.
-            fn clone_box(&self) -> Box<dyn FnBoxClone> {
-                Box::new(self.clone())
-            }
+    impl Clone for Box<dyn FnBoxClone> {
+        fn clone(&self) -> Box<dyn FnBoxClone> {
+            (**self).clone_box()
         }
+    }
 
-        impl Clone for Box<dyn FnBoxClone> {
-            fn clone(&self) -> Box<dyn FnBoxClone> {
-                (**self).clone_box()
-            }
-        }
+    /// Type alias for boxed up cloneable functions that take `Message`s and
+    /// return nothing. Mainly meant to increase readability of code.
+    pub(crate) type FnBox = Box<dyn FnBoxClone>;
+
+    /// Type alias for boxed up guards of Join Patterns.
+    ///
+    /// A guard takes references to the values of a candidate set of
+    /// `Message`s, in the order of the channels of the Join Pattern, and
+    /// decides whether the Join Pattern may fire with these `Message`s.
+    pub type GuardBox = Box<dyn Fn(&[&(dyn Any + Send)]) -> bool + Send>;
+
+    /// Type alias for shared handlers of panics in the functions of fired
+    /// Join Patterns, which are given the payload of the panic.
+    pub type PanicHandler = Arc<dyn Fn(Box<dyn Any + Send>) + Send + Sync>;
+}
 
-        /// Type alias for boxed up cloneable functions that take one `Message` and
-        /// return nothing. Mainly meant to increase readability of code.
-        pub type FnBox = Box<dyn FnBoxClone>;
+/// Type-level operations on tuples, used to keep track of the argument and
+/// return types of Join Patterns of arbitrary arity.
+///
+/// All traits in this module are implemented for tuples of up to
+/// `MAX_ARITY` elements.
+pub mod tuples {
+    use super::*;
+
+    /// Maximum number of elements supported in argument and reply tuples.
+    pub const MAX_ARITY: usize = 12;
+
+    /// Append a type to the end of a tuple.
+    pub trait Append<T> {
+        /// Tuple with `T` added as the last element.
+        type Output;
     }
 
-    /// Types and Traits for functions which take two arguments.
-    pub mod binary {
-        use super::*;
+    /// Construct a tuple from the values carried by `Message`s.
+    pub(crate) trait FromValues {
+        /// Downcast each value to the type of the tuple element at the same
+        /// position.
+        ///
+        /// # Panics
+        ///
+        /// Panics if the number or types of the values do not match the
+        /// tuple.
+        fn from_values(values: Vec<Payload>) -> Self;
+    }
 
-        /// Trait to allow boxed up functions that take two `Message`s and return
-        /// nothing to be cloned.
-        pub trait FnBoxClone: Fn(Message, Message) -> () + Send {
-            fn clone_box(&self) -> Box<dyn FnBoxClone>;
-        }
+    /// Tuple of the types of values replied to the synchronous channels of a
+    /// Join Pattern, in the order the channels were declared in.
+    pub trait Replies {
+        /// Type returned by the function of a Join Pattern with these
+        /// replies.
+        ///
+        /// A Join Pattern without synchronous channels returns `()`, one with
+        /// a single synchronous channel returns the reply directly and one
+        /// with multiple synchronous channels returns a tuple of replies.
+        type Output;
+
+        /// Send each reply in `output` through the return `Sender` at the
+        /// same position in `return_senders`.
+        ///
+        /// # Panics
+        ///
+        /// Panics if the number or types of the return `Sender`s do not match
+        /// the replies.
+        fn send_replies(output: Self::Output, return_senders: Vec<Payload>);
+
+        /// Fail each return `Sender` in `return_senders` with the given
+        /// error instead of replying.
+        ///
+        /// # Panics
+        ///
+        /// Panics if the number or types of the return `Sender`s do not match
+        /// the replies.
+        fn fail_replies(error: JunctionError, return_senders: Vec<Payload>);
+    }
 
-        impl<F> FnBoxClone for F
-        where
-            F: Fn(Message, Message) -> () + Send + Clone + 'static,
-        {
-            /// Proxy function to be able to implement the `Clone` trait on
-            /// boxed up functions that take two `Message`s and return nothing.
-            fn clone_box(&self) -> Box<dyn FnBoxClone> {
-                Box::new(self.clone())
+    macro_rules! impl_append {
+        ($($T:ident),*) => {
+            impl<$($T,)* Z> Append<Z> for ($($T,)*) {
+                type Output = ($($T,)* Z,);
             }
-        }
+        };
+    }
 
-        impl Clone for Box<dyn FnBoxClone> {
-            fn clone(&self) -> Box<dyn FnBoxClone> {
-                (**self).clone_box()
+    macro_rules! impl_from_values {
+        ($($T:ident),*) => {
+            impl<$($T),*> FromValues for ($($T,)*)
+            where
+                $($T: Any + Send),*
+            {
+                #[allow(unused_variables, unused_mut, clippy::unused_unit)]
+                fn from_values(values: Vec<Payload>) -> Self {
+                    let mut values = values.into_iter();
+
+                    ($(*values.next().unwrap().downcast::<$T>().unwrap(),)*)
+                }
             }
-        }
+        };
+    }
 
-        /// Type alias for boxed up cloneable functions that take two `Message`s and
-        /// return nothing. Mainly meant to increase readability of code.
-        pub type FnBox = Box<dyn FnBoxClone>;
+    impl_append!();
+    impl_append!(A);
+    impl_append!(A, B);
+    impl_append!(A, B, C);
+    impl_append!(A, B, C, D);
+    impl_append!(A, B, C, D, E);
+    impl_append!(A, B, C, D, E, F);
+    impl_append!(A, B, C, D, E, F, G);
+    impl_append!(A, B, C, D, E, F, G, H);
+    impl_append!(A, B, C, D, E, F, G, H, I);
+    impl_append!(A, B, C, D, E, F, G, H, I, J);
+    impl_append!(A, B, C, D, E, F, G, H, I, J, K);
+
+    impl_from_values!();
+    impl_from_values!(A);
+    impl_from_values!(A, B);
+    impl_from_values!(A, B, C);
+    impl_from_values!(A, B, C, D);
+    impl_from_values!(A, B, C, D, E);
+    impl_from_values!(A, B, C, D, E, F);
+    impl_from_values!(A, B, C, D, E, F, G);
+    impl_from_values!(A, B, C, D, E, F, G, H);
+    impl_from_values!(A, B, C, D, E, F, G, H, I);
+    impl_from_values!(A, B, C, D, E, F, G, H, I, J);
+    impl_from_values!(A, B, C, D, E, F, G, H, I, J, K);
+    impl_from_values!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+    macro_rules! impl_replies {
+        ($($R:ident $r:ident),*) => {
+            impl<$($R),*> Replies for ($($R,)*)
+            where
+                $($R: Any + Send),*
+            {
+                type Output = ($($R,)*);
+
+                fn send_replies(output: Self::Output, return_senders: Vec<Payload>) {
+                    let ($($r,)*) = output;
+                    let mut return_senders = return_senders.into_iter();
+
+                    $(
+                        // The waiting thread may have stopped waiting, in
+                        // which case the reply is simply discarded.
+                        let _ = return_senders
+                            .next()
+                            .unwrap()
+                            .downcast::<oneshot::Sender<$R>>()
+                            .unwrap()
+                            .send($r);
+                    )*
+                }
+
+                fn fail_replies(error: JunctionError, return_senders: Vec<Payload>) {
+                    let mut return_senders = return_senders.into_iter();
+
+                    $(
+                        return_senders
+                            .next()
+                            .unwrap()
+                            .downcast::<oneshot::Sender<$R>>()
+                            .unwrap()
+                            .fail(error.clone());
+                    )*
+                }
+            }
+        };
     }
 
-    /// Types and Traits for functions which take three arguments.
-    pub mod ternary {
-        use super::*;
+    impl Replies for () {
+        type Output = ();
 
-        /// Trait to allow boxed up functions that take three `Message`s and return
-        /// nothing to be cloned.
-        pub trait FnBoxClone: Fn(Message, Message, Message) -> () + Send {
-            fn clone_box(&self) -> Box<dyn FnBoxClone>;
-        }
+        fn send_replies(_output: (), _return_senders: Vec<Payload>) {}
 
-        impl<F> FnBoxClone for F
-        where
-            F: Fn(Message, Message, Message) -> () + Send + Clone + 'static,
-        {
-            /// Proxy function to be able to implement the `Clone` trait on
-            /// boxed up functions that take three `Message`s and return nothing.
-            fn clone_box(&self) -> Box<dyn FnBoxClone> {
-                Box::new(self.clone())
-            }
-        }
+        fn fail_replies(_error: JunctionError, _return_senders: Vec<Payload>) {}
+    }
 
-        impl Clone for Box<dyn FnBoxClone> {
-            fn clone(&self) -> Box<dyn FnBoxClone> {
-                (**self).clone_box()
-            }
+    impl<R> Replies for (R,)
+    where
+        R: Any + Send,
+    {
+        type Output = R;
+
+        fn send_replies(output: R, return_senders: Vec<Payload>) {
+            let return_sender = return_senders.into_iter().next().unwrap();
+
+            // The waiting thread may have stopped waiting, in which case the
+            // reply is simply discarded.
+            let _ = return_sender
+                .downcast::<oneshot::Sender<R>>()
+                .unwrap()
+                .send(output);
         }
 
-        /// Type alias for boxed up cloneable functions that take three `Message`s and
-        /// return nothing. Mainly meant to increase readability of code.
-        pub type FnBox = Box<dyn FnBoxClone>;
+        fn fail_replies(error: JunctionError, return_senders: Vec<Payload>) {
+            let return_sender = return_senders.into_iter().next().unwrap();
+
+            return_sender
+                .downcast::<oneshot::Sender<R>>()
+                .unwrap()
+                .fail(error);
+        }
     }
+
+    impl_replies!(R1 r1, R2 r2);
+    impl_replies!(R1 r1, R2 r2, R3 r3);
+    impl_replies!(R1 r1, R2 r2, R3 r3, R4 r4);
+    impl_replies!(R1 r1, R2 r2, R3 r3, R4 r4, R5 r5);
+    impl_replies!(R1 r1, R2 r2, R3 r3, R4 r4, R5 r5, R6 r6);
+    impl_replies!(R1 r1, R2 r2, R3 r3, R4 r4, R5 r5, R6 r6, R7 r7);
+    impl_replies!(R1 r1, R2 r2, R3 r3, R4 r4, R5 r5, R6 r6, R7 r7, R8 r8);
+    impl_replies!(R1 r1, R2 r2, R3 r3, R4 r4, R5 r5, R6 r6, R7 r7, R8 r8, R9 r9);
+    impl_replies!(R1 r1, R2 r2, R3 r3, R4 r4, R5 r5, R6 r6, R7 r7, R8 r8, R9 r9, R10 r10);
+    impl_replies!(R1 r1, R2 r2, R3 r3, R4 r4, R5 r5, R6 r6, R7 r7, R8 r8, R9 r9, R10 r10, R11 r11);
+    impl_replies!(R1 r1, R2 r2, R3 r3, R4 r4, R5 r5, R6 r6, R7 r7, R8 r8, R9 r9, R10 r10, R11 r11, R12 r12);
 }
 
 /// Adds specific ID types for the various IDs that are used in the crate.
 pub mod ids {
     use std::sync::atomic::{AtomicUsize, Ordering};
 
+    /// Globally synchronized counter to ensure that no two channels will have
+    /// the same ID, without having to ask their Junction for one.
+    static LATEST_CHANNEL_ID: AtomicUsize = AtomicUsize::new(0);
+
     /// ID to identify a channel within a Join Pattern.
-    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
     pub struct ChannelId(usize);
 
     impl ChannelId {
-        pub(crate) fn new(value: usize) -> ChannelId {
-            ChannelId(value)
-        }
-
-        /// Increment the internal value of the channel ID.
-        pub(crate) fn increment(&mut self) {
-            self.0 += 1;
+        pub(crate) fn new() -> ChannelId {
+            ChannelId(LATEST_CHANNEL_ID.fetch_add(1, Ordering::Relaxed))
         }
     }
 
+    /// Globally synchronized counter to ensure that no two Join Patterns will
+    /// have the same ID, without having to ask their Junction for one.
+    static LATEST_JOIN_PATTERN_ID: AtomicUsize = AtomicUsize::new(0);
+
     /// ID to identify a Join Pattern within a Junction.
-    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+    ///
+    /// IDs are ordered by the time at which the Join Patterns were added to
+    /// their Junction.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
     pub struct JoinPatternId(usize);
 
     impl JoinPatternId {
+        pub(crate) fn new() -> JoinPatternId {
+            JoinPatternId(LATEST_JOIN_PATTERN_ID.fetch_add(1, Ordering::Relaxed))
+        }
+
         /// Increment the internal value of the Join Pattern ID.
+        #[cfg(test)]
         pub(crate) fn increment(&mut self) {
             self.0 += 1;
         }
@@ -253,4 +740,19 @@ pub mod ids {
             JunctionId(LATEST_JUNCTION_ID.fetch_add(1, Ordering::Relaxed))
         }
     }
+
+    /// Globally synchronized counter to ensure that no two `Message`s will
+    /// have the same ID.
+    static LATEST_MESSAGE_ID: AtomicUsize = AtomicUsize::new(0);
+
+    /// ID for a `Message` sent on a synchronous channel, to identify it
+    /// within the Junction.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct MessageId(usize);
+
+    impl MessageId {
+        pub(crate) fn new() -> MessageId {
+            MessageId(LATEST_MESSAGE_ID.fetch_add(1, Ordering::Relaxed))
+        }
+    }
 }